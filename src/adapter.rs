@@ -0,0 +1,681 @@
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    time::Duration,
+};
+
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{config::Kind, harness::Target};
+
+/// Built-in adapters, overridable by entries with the same name in `tools.yaml`
+const PRESETS: &str = r#"
+# Exit code 1: Undefined Behavior, 101: the program panicked; both are also the exit codes of
+# a missing Miri or of a failed build, which report no finding
+- name: miri
+  command: cargo
+  args: [miri, run, --quiet, --manifest-path, "{manifest}"]
+  finding_codes: [1, 101]
+  parser:
+    type: miri
+
+# Clippy wraps rustc, whose fingerprints would then invalidate the shared build
+- name: clippy
+  command: cargo
+  args: [clippy, --quiet, --manifest-path, "{manifest}", --message-format=json, --, --cap-lints, warn]
+  target: isolated
+  parser:
+    type: diagnostics
+    codes: "^clippy::"
+
+- name: rustc
+  command: cargo
+  args: [check, --quiet, --manifest-path, "{manifest}", --message-format=json]
+  parser:
+    type: diagnostics
+
+# Executes the binary: a panic (exit code 101) or a crash reported by cargo is a finding; a
+# failed build exits with 101 as well, without any finding. A clean exit does not rule out UB or
+# a leak, so it is only an oracle of panics
+- name: run
+  command: cargo
+  args: [run, --quiet, --manifest-path, "{manifest}"]
+  finding_codes: [101]
+  observes: [panic]
+  parser:
+    type: regex
+    stream: stderr
+    pattern: 'panicked at (?P<file>(?:[\w.\-]*/)*main\.rs):(?P<line>\d+)|(?P<message>signal: \d+, SIG\w+)'
+"#;
+
+/// Adapters of the evaluated tools, loaded from `tools.yaml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Adapters(Vec<Adapter>);
+
+impl Adapters {
+    pub(crate) fn from_file<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        info!("Adapters from file: {}", path.display());
+        let cnt = std::fs::read_to_string(path).expect("File not found");
+        serde_yaml::from_str(&cnt)
+            .unwrap_or_else(|err| panic!("File Content Format Error: {}: {}", path.display(), err))
+    }
+
+    /// Built-in adapters of Miri, Clippy, rustc, and of the execution of the binary
+    pub(crate) fn presets() -> Self {
+        serde_yaml::from_str(PRESETS).expect("Invalid preset adapters")
+    }
+
+    /// Resolve `tool` into an adapter
+    ///
+    /// `tool` is either the name of an adapter in `tools.yaml`, the name of a preset (`miri`,
+    /// `clippy`, `rustc`, `run`), or the path of an executable invoked as `<tool> <harness-dir>` whose
+    /// stdout is scanned for `main.rs:<line>` locations.
+    pub(crate) fn resolve(&self, tool: &str) -> Adapter {
+        let presets = Adapters::presets();
+        match self
+            .iter()
+            .chain(presets.iter())
+            .find(|adapter| adapter.name == tool)
+        {
+            Some(adapter) => adapter.clone(),
+            None => {
+                crate::utils::is_executable(tool);
+                Adapter::executable(PathBuf::from(tool))
+            }
+        }
+    }
+}
+
+impl Deref for Adapters {
+    type Target = Vec<Adapter>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Adapters {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// How to invoke a tool and how to parse its output
+///
+/// `command`, `args`, `env` and `workdir` are templates, in which `{harness}` is replaced by the
+/// harness directory and `{manifest}` by its `Cargo.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Adapter {
+    pub(crate) name: String,
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    workdir: Option<String>,
+    /// Exit codes of a successful analysis (whether bugs were found or not)
+    #[serde(default = "Adapter::default_success_codes")]
+    success_codes: Vec<i32>,
+    /// Exit codes of a successful analysis only if a finding is parsed, for tools which exit
+    /// with the same code on a bug and on their own failure
+    #[serde(default)]
+    finding_codes: Vec<i32>,
+    /// Resource limits of the tool, overridden by the command line
+    #[serde(default)]
+    pub(crate) limits: Limits,
+    /// `CARGO_TARGET_DIR` of the tool, overridden by `env`
+    #[serde(default)]
+    pub(crate) target: Target,
+    /// Kinds of testcases whose bug and its absence the tool can both observe, when used as an
+    /// oracle [default: all]
+    #[serde(default = "Adapter::default_observes")]
+    pub(crate) observes: Vec<Kind>,
+    parser: Parser,
+}
+
+impl Adapter {
+    /// Usage: `<tool> <harness-dir>`
+    pub(crate) fn executable(tool: PathBuf) -> Self {
+        Adapter {
+            name: tool.file_stem().unwrap().to_string_lossy().to_string(),
+            command: tool.to_string_lossy().to_string(),
+            args: vec![String::from("{harness}")],
+            env: BTreeMap::new(),
+            workdir: None,
+            success_codes: Adapter::default_success_codes(),
+            finding_codes: Vec::new(),
+            limits: Limits::default(),
+            // 未知的工具未必遵循 CARGO_TARGET_DIR
+            target: Target::Harness,
+            observes: Adapter::default_observes(),
+            parser: Parser::Regex {
+                stream: Stream::Stdout,
+                // main.rs 须为完整的文件名, 不匹配 domain.rs 等
+                pattern: Pattern::new(
+                    r"(?:^|[^\w.])(?P<file>(?:[\w.\-]*/)*main\.rs):(?P<line>\d+)",
+                ),
+            },
+        }
+    }
+
+    fn default_success_codes() -> Vec<i32> {
+        vec![0]
+    }
+
+    fn default_observes() -> Vec<Kind> {
+        Kind::ALL.to_vec()
+    }
+
+    /// Fill the placeholders of `template`
+    fn render(template: &str, harness: &Path) -> String {
+        template
+            .replace("{harness}", &harness.to_string_lossy())
+            .replace("{manifest}", &harness.join("Cargo.toml").to_string_lossy())
+    }
+
+    /// Build the command analysing `harness`, building into `target_dir`
+    pub(crate) fn command(&self, harness: &Path, target_dir: Option<&Path>) -> Command {
+        let mut command = Command::new(Adapter::render(&self.command, harness));
+        command.args(self.args.iter().map(|arg| Adapter::render(arg, harness)));
+        if let Some(target_dir) = target_dir {
+            command.env("CARGO_TARGET_DIR", target_dir);
+        }
+        command.envs(
+            self.env
+                .iter()
+                .map(|(key, val)| (key, Adapter::render(val, harness))),
+        );
+        if let Some(workdir) = &self.workdir {
+            command.current_dir(Adapter::render(workdir, harness));
+        }
+        command
+    }
+
+    /// Check if the tool finished its analysis, reporting `findings`
+    pub(crate) fn is_success(&self, output: &Output, findings: &[Finding]) -> bool {
+        output.status.code().is_some_and(|code| {
+            self.success_codes.contains(&code)
+                || (self.finding_codes.contains(&code) && !findings.is_empty())
+        })
+    }
+
+    /// Parse the findings reported in `output`
+    pub(crate) fn parse(&self, output: &Output) -> Vec<Finding> {
+        self.parser.parse(output)
+    }
+}
+
+/// Resource limits of a tool invocation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Limits {
+    /// Wall-clock timeout in seconds
+    pub timeout: Option<u64>,
+    /// Address space limit in MiB (`RLIMIT_AS`)
+    pub memory: Option<u64>,
+    /// CPU time limit in seconds (`RLIMIT_CPU`)
+    pub cpu: Option<u64>,
+}
+
+impl Limits {
+    /// Limits of `self`, falling back to `other` for the unset ones
+    pub(crate) fn or(self, other: Limits) -> Limits {
+        Limits {
+            timeout: self.timeout.or(other.timeout),
+            memory: self.memory.or(other.memory),
+            cpu: self.cpu.or(other.cpu),
+        }
+    }
+}
+
+/// Output stream parsed by a [`Parser`]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Stream {
+    #[default]
+    Stdout,
+    Stderr,
+    Both,
+}
+
+impl Stream {
+    fn select(&self, output: &Output) -> String {
+        match self {
+            Stream::Stdout => String::from_utf8_lossy(&output.stdout).to_string(),
+            Stream::Stderr => String::from_utf8_lossy(&output.stderr).to_string(),
+            Stream::Both => format!(
+                "{}\n{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+    }
+}
+
+/// Parser of the tool output into [`Finding`]s
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum Parser {
+    /// Every match of `pattern` is a finding; the named groups `kind`, `file`, `line` and
+    /// `message` are optional
+    Regex {
+        #[serde(default)]
+        stream: Stream,
+        pattern: Pattern,
+    },
+    /// The output is a JSON document (or JSON lines); `findings` selects the findings and the
+    /// other paths are relative to each finding, e.g. `spans[0].line_start`
+    Json {
+        #[serde(default)]
+        stream: Stream,
+        #[serde(default)]
+        findings: Option<String>,
+        #[serde(default)]
+        kind: Option<String>,
+        #[serde(default)]
+        file: Option<String>,
+        #[serde(default)]
+        line: Option<String>,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// Undefined Behavior, leaks and panics reported by `cargo miri run` on stderr, located at
+    /// the first `main.rs` span of their backtrace; other errors, e.g. unsupported operations or
+    /// errors of cargo or rustup, are not findings
+    Miri,
+    /// `--message-format=json` diagnostics of rustc and Clippy; `codes` is a regex filtering
+    /// the lint codes
+    Diagnostics {
+        #[serde(default)]
+        codes: Option<Pattern>,
+    },
+}
+
+/// Regex of an adapter, compiled once when the adapter is loaded
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern(Regex);
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        Pattern(Regex::new(pattern).expect("Invalid regex in tool adapter"))
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Deref for Pattern {
+    type Target = Regex;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Parser {
+    pub(crate) fn parse(&self, output: &Output) -> Vec<Finding> {
+        match self {
+            Parser::Regex { stream, pattern } => {
+                let text = stream.select(output);
+                pattern
+                    .captures_iter(&text)
+                    .map(|caps| Finding {
+                        kind: caps.name("kind").map(|m| m.as_str().to_string()),
+                        file: caps.name("file").map(|m| m.as_str().to_string()),
+                        line: caps.name("line").and_then(|m| m.as_str().parse().ok()),
+                        message: caps
+                            .name("message")
+                            .unwrap_or_else(|| caps.get(0).unwrap())
+                            .as_str()
+                            .to_string(),
+                    })
+                    .collect()
+            }
+            Parser::Json {
+                stream,
+                findings,
+                kind,
+                file,
+                line,
+                message,
+            } => {
+                let text = stream.select(output);
+                // 整体解析失败时按 JSON lines 逐行解析
+                let docs: Vec<Value> = match serde_json::from_str(&text) {
+                    Ok(doc) => vec![doc],
+                    Err(_) => text
+                        .lines()
+                        .filter_map(|line| serde_json::from_str(line).ok())
+                        .collect(),
+                };
+                let field = |value: &Value, path: &Option<String>| -> Option<Value> {
+                    path.as_ref()
+                        .and_then(|path| json_path(value, path).into_iter().next())
+                        .cloned()
+                };
+                docs.iter()
+                    .flat_map(|doc| match findings {
+                        Some(path) => json_path(doc, path),
+                        None => vec![doc],
+                    })
+                    .map(|value| Finding {
+                        kind: field(value, kind).map(json_to_string),
+                        file: field(value, file).map(json_to_string),
+                        line: field(value, line).and_then(|line| match line {
+                            Value::Number(num) => num.as_u64().map(|n| n as usize),
+                            other => json_to_string(other).parse().ok(),
+                        }),
+                        message: field(value, message)
+                            .map(json_to_string)
+                            .unwrap_or_else(|| value.to_string()),
+                    })
+                    .collect()
+            }
+            Parser::Miri => Parser::parse_miri(&String::from_utf8_lossy(&output.stderr)),
+            Parser::Diagnostics { codes } => Parser::parse_diagnostics(
+                &String::from_utf8_lossy(&output.stdout),
+                codes.as_deref(),
+            ),
+        }
+    }
+
+    /// Parse `error: <kind>: <message>` and `thread '<name>' panicked at <location>:` reports
+    fn parse_miri(stderr: &str) -> Vec<Finding> {
+        // unsupported operation 表示 Miri 无法继续解释, 不是检测到的缺陷
+        const KINDS: [&str; 2] = ["Undefined Behavior", "memory leaked"];
+        let location = Regex::new(r"(?:-->|\bat)\s+(\S*main\.rs):(\d+)").unwrap();
+        let panic = Regex::new(r"^thread '.*' panicked at (\S*main\.rs):(\d+)").unwrap();
+        let mut findings: Vec<Finding> = Vec::new();
+        let mut lines = stderr.lines().peekable();
+        while let Some(line) = lines.next() {
+            if let Some(caps) = panic.captures(line) {
+                findings.push(Finding {
+                    kind: Some(String::from("panic")),
+                    file: Some(caps[1].to_string()),
+                    line: caps[2].parse().ok(),
+                    message: lines.next().unwrap_or_default().to_string(),
+                });
+                continue;
+            }
+            // 其他错误 (如编译失败, 未安装 Miri) 不是发现, 由退出码判为 Err
+            let Some((kind, message)) = line
+                .strip_prefix("error: ")
+                .and_then(|error| error.split_once(": "))
+                .filter(|(kind, _)| KINDS.contains(kind))
+            else {
+                continue;
+            };
+            let mut finding = Finding {
+                kind: Some(kind.to_string()),
+                file: None,
+                line: None,
+                message: message.to_string(),
+            };
+            // 在回溯中查找首个位于 main.rs 的位置
+            while let Some(next) = lines.next_if(|next| !next.starts_with("error")) {
+                if let (None, Some(caps)) = (&finding.line, location.captures(next)) {
+                    finding.file = Some(caps[1].to_string());
+                    finding.line = caps[2].parse().ok();
+                }
+            }
+            findings.push(finding);
+        }
+        findings
+    }
+
+    /// Parse the `compiler-message`s located in `main.rs`
+    fn parse_diagnostics(stdout: &str, codes: Option<&Regex>) -> Vec<Finding> {
+        stdout
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter(|msg| msg["reason"] == "compiler-message")
+            .map(|msg| msg["message"].clone())
+            .filter_map(|msg| {
+                let code = msg["code"]["code"].as_str()?.to_string();
+                if codes.is_some_and(|codes| !codes.is_match(&code)) {
+                    return None;
+                }
+                let spans = msg["spans"].as_array()?;
+                let span = spans
+                    .iter()
+                    .find(|span| span["is_primary"] == true)
+                    .or_else(|| spans.first())?;
+                Some(Finding {
+                    kind: Some(code),
+                    file: span["file_name"].as_str().map(str::to_string),
+                    line: span["line_start"].as_u64().map(|line| line as usize),
+                    message: msg["message"].as_str().unwrap_or_default().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn json_to_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Select the values at `path` in `value`
+///
+/// Supported syntax: `$` (optional root), `.key`, `[n]` and `[*]`, e.g. `$.items[*].spans[0]`.
+pub(crate) fn json_path<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    let re = Regex::new(r"\[(\*|\d+)\]|([^.\[\]$]+)").unwrap();
+    let mut values = vec![value];
+    for caps in re.captures_iter(path) {
+        values = values
+            .into_iter()
+            .flat_map(|value| -> Vec<&Value> {
+                match (caps.get(1).map(|m| m.as_str()), caps.get(2)) {
+                    (Some("*"), _) => value
+                        .as_array()
+                        .map(|array| array.iter().collect())
+                        .unwrap_or_default(),
+                    (Some(idx), _) => value
+                        .get(idx.parse::<usize>().unwrap())
+                        .into_iter()
+                        .collect(),
+                    (None, Some(key)) => value.get(key.as_str()).into_iter().collect(),
+                    (None, None) => unreachable!(),
+                }
+            })
+            .collect();
+    }
+    values
+}
+
+/// A bug reported by a tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Finding {
+    pub(crate) kind: Option<String>,
+    pub(crate) file: Option<String>,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl Finding {
+    /// Check if the finding is located at one of `lines` of the harness' `src/main.rs`
+    ///
+    /// Findings of a program without SINK lines match anywhere in `main.rs`.
+    pub(crate) fn hits(&self, lines: &[usize]) -> bool {
+        match self.line {
+            Some(line) => self.in_main() && (lines.is_empty() || lines.contains(&line)),
+            None => false,
+        }
+    }
+
+    /// Check if the finding is located in a file named `main.rs`
+    pub(crate) fn in_main(&self) -> bool {
+        self.file
+            .as_deref()
+            .is_some_and(|file| file == "main.rs" || file.ends_with("/main.rs"))
+    }
+}
+
+/// How a tool invocation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum Status {
+    Finished,
+    Timeout,     // 超出 wall-clock 时限, 进程组已被杀死
+    OutOfMemory, // 超出内存限制或被 OOM killer 杀死
+}
+
+/// Result of a tool invocation
+pub(crate) struct Execution {
+    pub(crate) output: Output,
+    pub(crate) status: Status,
+    /// Wall time of the invocation
+    pub(crate) elapsed: Duration,
+    pub(crate) success: bool,
+    pub(crate) findings: Vec<Finding>,
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::process::ExitStatusExt as _;
+
+    use super::*;
+
+    fn output(stdout: &str) -> Output {
+        Output {
+            status: std::process::ExitStatus::from_raw(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        }
+    }
+
+    #[test]
+    fn test_adapters_from_file() {
+        let adapters = Adapters::from_file("config/tools.yaml");
+        assert!(!adapters.is_empty());
+    }
+
+    #[test]
+    fn test_regex_parser() {
+        let adapter = Adapter::executable("/bin/true".into());
+        let findings = adapter.parse(&output(
+            "banner\n --> src/main.rs:7:13\n/tmp/harness-0/src/main.rs:12: UAF\nlib.rs:3:1",
+        ));
+        let lines: Vec<_> = findings.iter().map(|f| f.line.unwrap()).collect();
+        assert_eq!(lines, vec![7, 12]);
+        assert!(findings[1].hits(&[12]));
+        assert!(!findings[1].hits(&[7]));
+        // 以 main.rs 结尾的其他文件不是目标文件
+        assert!(adapter.parse(&output("src/domain.rs:3: UAF")).is_empty());
+    }
+
+    #[test]
+    fn test_json_parser() {
+        let parser: Parser = serde_yaml::from_str(
+            r#"
+type: json
+findings: $.bugs[*]
+kind: kind
+file: location.file
+line: location.line
+message: msg
+"#,
+        )
+        .unwrap();
+        let findings = parser.parse(&output(
+            r#"{"bugs": [{"kind": "UseAfterFree", "msg": "use of freed ptr", "location": {"file": "src/main.rs", "line": 9}}]}"#,
+        ));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind.as_deref(), Some("UseAfterFree"));
+        assert_eq!(findings[0].line, Some(9));
+        assert!(findings[0].hits(&[9]));
+    }
+
+    fn fixture(name: &str) -> Output {
+        let cnt = std::fs::read(format!("fixtures/{}", name)).unwrap();
+        let mut output = output("");
+        match name.ends_with(".stderr") {
+            true => output.stderr = cnt,
+            false => output.stdout = cnt,
+        }
+        output
+    }
+
+    fn locate(adapter: &Adapter, name: &str) -> Vec<(String, usize)> {
+        adapter
+            .parse(&fixture(name))
+            .into_iter()
+            .map(|f| (f.kind.unwrap(), f.line.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_miri_preset() {
+        let miri = Adapters::default().resolve("miri");
+        let ub = String::from("Undefined Behavior");
+        assert_eq!(locate(&miri, "miri-ub.stderr"), vec![(ub.clone(), 8)]);
+        assert_eq!(locate(&miri, "miri-std.stderr"), vec![(ub, 12)]);
+        assert_eq!(
+            locate(&miri, "miri-leak.stderr"),
+            vec![(String::from("memory leaked"), 5)]
+        );
+        assert_eq!(
+            locate(&miri, "miri-panic.stderr"),
+            vec![(String::from("panic"), 9)]
+        );
+
+        // 未安装 Miri 或编译失败时以相同的退出码结束, 但没有发现
+        let mut failure = output("");
+        failure.status = std::process::ExitStatus::from_raw(1 << 8);
+        failure.stderr = b"error: 'cargo-miri' is not installed for the toolchain\n".to_vec();
+        assert!(miri.parse(&failure).is_empty());
+        assert!(!miri.is_success(&failure, &[]));
+        // Miri 不支持的操作不是缺陷
+        failure.stderr = b"error: unsupported operation: can't call foreign function `f`\n  --> src/main.rs:4:5\n".to_vec();
+        assert!(miri.parse(&failure).is_empty());
+        let mut ub = fixture("miri-ub.stderr");
+        ub.status = failure.status;
+        assert!(miri.is_success(&ub, &miri.parse(&ub)));
+    }
+
+    #[test]
+    fn test_diagnostics_presets() {
+        let clippy = Adapters::default().resolve("clippy");
+        assert_eq!(
+            locate(&clippy, "clippy.json"),
+            vec![
+                (String::from("clippy::absurd_extreme_comparisons"), 8),
+                (String::from("clippy::useless_vec"), 7)
+            ]
+        );
+        let rustc = Adapters::default().resolve("rustc");
+        assert_eq!(
+            locate(&rustc, "rustc.json"),
+            vec![(String::from("unused_comparisons"), 8)]
+        );
+    }
+}
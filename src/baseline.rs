@@ -0,0 +1,246 @@
+use std::{collections::BTreeMap, path::Path};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use tabled::Tabled;
+
+use crate::{utils, EvalResult, EvalRow};
+
+/// Key of the variant of `row` in the baseline: the fingerprint of its sources in the tool
+/// directory `dir`, or its number if they are missing
+fn key(dir: &Path, row: &EvalRow) -> String {
+    utils::fingerprint(&dir.join(&row.id).join(&row.variant)).unwrap_or_else(|| row.variant.clone())
+}
+
+/// Variants each tool is known to miss or false-alarm on, by tool and testcase ID:
+///
+/// ```yaml
+/// miri:
+///   sp-system-01:
+///     misses:
+///       9c1e4f0a3b7d2e65: 0003-1-0
+///     false_alarms:
+///       0d5b8a7e6f1c2439: 0000-0-0
+/// ```
+///
+/// Variants are keyed by the fingerprint of their sources, since their numbers depend on the
+/// seed and on the variants a run skips; the number of the variant is kept for reference.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Baseline(BTreeMap<String, BTreeMap<String, Expectations>>);
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Expectations {
+    /// Variants whose POS program is not detected (FN)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    misses: BTreeMap<String, String>,
+    /// Variants whose NEG program is reported (FP)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    false_alarms: BTreeMap<String, String>,
+}
+
+/// Difference of a run with the baseline on one variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Change {
+    NewMiss,
+    NewFalseAlarm,
+    FixedMiss,
+    FixedFalseAlarm,
+}
+
+impl Change {
+    pub(crate) fn is_regression(self) -> bool {
+        matches!(self, Change::NewMiss | Change::NewFalseAlarm)
+    }
+}
+
+#[derive(Tabled)]
+pub(crate) struct BaselineReport {
+    #[tabled(rename = "工具")]
+    tool: String,
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "变体")]
+    variant: String,
+    #[tabled(rename = "变化")]
+    change: String,
+    #[tabled(skip)]
+    pub(crate) kind: Change,
+}
+
+impl Baseline {
+    pub(crate) fn from_file<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        info!("Baseline from file: {}", path.display());
+        let cnt = std::fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+        serde_yaml::from_str(&cnt)
+            .unwrap_or_else(|err| panic!("File Content Format Error: {}: {}", path.display(), err))
+    }
+
+    pub(crate) fn to_file<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        let cnt = serde_yaml::to_string(self).expect("Baseline serialization failed");
+        std::fs::write(path, cnt).expect("std::fs::write failed");
+    }
+
+    /// Misses and false alarms of every tool in the run of the output directory `dir`
+    pub(crate) fn of(tools: &[String], rows: &[&EvalRow], dir: &Path) -> Self {
+        let mut baseline = Baseline::default();
+        for (i, tool) in tools.iter().enumerate() {
+            for row in rows {
+                let res = row.results[i];
+                if !matches!(res.0, EvalResult::FN) && !matches!(res.1, EvalResult::FP) {
+                    continue;
+                }
+                let expectations = baseline
+                    .0
+                    .entry(tool.clone())
+                    .or_default()
+                    .entry(row.id.clone())
+                    .or_default();
+                let key = key(&dir.join(tool), row);
+                if matches!(res.0, EvalResult::FN) {
+                    expectations.misses.insert(key.clone(), row.variant.clone());
+                }
+                if matches!(res.1, EvalResult::FP) {
+                    expectations.false_alarms.insert(key, row.variant.clone());
+                }
+            }
+        }
+        baseline
+    }
+
+    /// New misses and false alarms of the run of the output directory `dir`, and the known ones
+    /// it no longer has
+    ///
+    /// A known failure is fixed only if the program now gets a TP (or TN) verdict; errors and
+    /// timeouts leave it as is. Testcases and tools absent from the run are ignored.
+    pub(crate) fn compare(
+        &self,
+        tools: &[String],
+        rows: &[&EvalRow],
+        dir: &Path,
+    ) -> Vec<BaselineReport> {
+        let empty = Expectations::default();
+        let mut reports = Vec::new();
+        for (i, tool) in tools.iter().enumerate() {
+            for row in rows {
+                let expected = self
+                    .0
+                    .get(tool)
+                    .and_then(|testcases| testcases.get(&row.id))
+                    .unwrap_or(&empty);
+                let key = key(&dir.join(tool), row);
+                let (missed, alarmed) = (
+                    expected.misses.contains_key(&key),
+                    expected.false_alarms.contains_key(&key),
+                );
+                let res = row.results[i];
+                let changes = [
+                    (matches!(res.0, EvalResult::FN) && !missed, Change::NewMiss),
+                    (
+                        matches!(res.1, EvalResult::FP) && !alarmed,
+                        Change::NewFalseAlarm,
+                    ),
+                    (matches!(res.0, EvalResult::TP) && missed, Change::FixedMiss),
+                    (
+                        matches!(res.1, EvalResult::TN) && alarmed,
+                        Change::FixedFalseAlarm,
+                    ),
+                ];
+                for (_, kind) in changes.into_iter().filter(|(changed, _)| *changed) {
+                    reports.push(BaselineReport {
+                        tool: tool.clone(),
+                        id: row.id.clone(),
+                        variant: row.variant.clone(),
+                        change: String::from(match kind {
+                            Change::NewMiss => "New miss (FN)",
+                            Change::NewFalseAlarm => "New false alarm (FP)",
+                            Change::FixedMiss => "Fixed miss (TP)",
+                            Change::FixedFalseAlarm => "Fixed false alarm (TN)",
+                        }),
+                        kind,
+                    });
+                }
+            }
+        }
+        reports
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EvalResults;
+
+    #[test]
+    fn test_baseline() {
+        let row = |variant, pos, neg| EvalRow::test(variant, None, &[], EvalResults(pos, neg));
+        // 每个变体的源码, 两次运行中编号不同
+        let run = |name: &str, variants: [(&str, &str); 3]| {
+            let dir = Path::new("./output/baseline").join(name);
+            for (variant, code) in variants {
+                let path = dir.join("miri").join("sp-system-01").join(variant);
+                std::fs::create_dir_all(&path).unwrap();
+                let code = format!("// Variant: {}\n{}", variant, code);
+                std::fs::write(path.join("POS.rs"), &code).unwrap();
+                std::fs::write(path.join("NEG.rs"), &code).unwrap();
+            }
+            dir
+        };
+        let tools = [String::from("miri")];
+        let dir = run(
+            "a",
+            [("0000-0-0", "a"), ("0001-1-1", "b"), ("0002-1-1", "c")],
+        );
+        let before = [
+            row("0000-0-0", EvalResult::TP, EvalResult::TN),
+            row("0001-1-1", EvalResult::FN, EvalResult::TN),
+            row("0002-1-1", EvalResult::TP, EvalResult::FP),
+        ];
+        let baseline = Baseline::of(&tools, &before.iter().collect::<Vec<_>>(), &dir);
+        let yaml = serde_yaml::to_string(&baseline).unwrap();
+        let fingerprint = |variant: &str| {
+            utils::fingerprint(&dir.join("miri/sp-system-01").join(variant)).unwrap()
+        };
+        assert_eq!(
+            yaml,
+            format!(
+                "miri:\n  sp-system-01:\n    misses:\n      {}: 0001-1-1\n    false_alarms:\n      {}: 0002-1-1\n",
+                fingerprint("0001-1-1"),
+                fingerprint("0002-1-1")
+            )
+        );
+        assert_eq!(serde_yaml::from_str::<Baseline>(&yaml).unwrap(), baseline);
+
+        let dir = run(
+            "b",
+            [("0000-0-0", "a"), ("0003-1-1", "b"), ("0001-1-1", "c")],
+        );
+        let after = [
+            row("0000-0-0", EvalResult::FN, EvalResult::TN),
+            row("0003-1-1", EvalResult::TP, EvalResult::TN),
+            // 已知误报在出错时不视为修复
+            row("0001-1-1", EvalResult::Err, EvalResult::Err),
+        ];
+        let reports = baseline.compare(&tools, &after.iter().collect::<Vec<_>>(), &dir);
+        let changes: Vec<_> = reports
+            .iter()
+            .map(|r| (r.variant.as_str(), r.kind))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("0000-0-0", Change::NewMiss),
+                ("0003-1-1", Change::FixedMiss)
+            ]
+        );
+        assert!(reports[0].kind.is_regression() && !reports[1].kind.is_regression());
+        std::fs::remove_dir_all("./output/baseline").unwrap();
+    }
+}
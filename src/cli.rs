@@ -1,12 +1,33 @@
 use std::path::PathBuf;
 
-use clap::Parser;
-use eval::Evaluator;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use eval::{Evaluator, Format, Limits, Options, Selection};
+
+/// Exit status of `run` when a tool misses or false-alarms on variants not in the baseline
+const REGRESSION_STATUS: i32 = 3;
+
 #[derive(Parser)]
 pub(crate) struct Cli {
-    /// Tools to be evaluated
-    tool: PathBuf,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Evaluate tools on generated variants, or on a frozen corpus
+    Run(RunArgs),
+    /// Generate a frozen variant corpus, independently of any tool
+    Generate(GenerateArgs),
+    /// Check the configuration files for format and semantic errors
+    Validate(ValidateArgs),
+    /// Check with a reference oracle that each POS program misbehaves and each NEG one does not
+    Verify(VerifyArgs),
+    /// Compare the verdicts of two runs, per testcase and per variant (matched by source)
+    Diff(DiffArgs),
+}
 
+#[derive(Args)]
+struct GenerationArgs {
     /// Configuration file
     #[arg(short, long, value_name = "DIR")]
     config: Option<PathBuf>,
@@ -15,6 +36,22 @@ pub(crate) struct Cli {
     #[arg(short, long, value_parser, num_args=1..)]
     indices: Vec<usize>,
 
+    /// Select the testcases by ID (glob, or regex between slashes)
+    #[arg(long = "id", value_name = "PATTERN", num_args = 1..)]
+    ids: Vec<String>,
+
+    /// Select the testcases by tag, e.g. 'SP(Initialized)' (glob, or regex between slashes)
+    #[arg(long = "tag", value_name = "PATTERN", num_args = 1..)]
+    tags: Vec<String>,
+
+    /// Select the testcases by feature, e.g. 'Function std::alloc::*' (glob, or regex between slashes)
+    #[arg(long = "feature", value_name = "PATTERN", num_args = 1..)]
+    features: Vec<String>,
+
+    /// Select the testcases by UB category, e.g. 'Dangling*' (glob, or regex between slashes)
+    #[arg(long = "ub", value_name = "PATTERN", num_args = 1..)]
+    ubs: Vec<String>,
+
     /// Expression nesting depth
     #[arg(short, long, value_name = "NUM", default_value_t = 3)]
     depth: usize,
@@ -23,16 +60,276 @@ pub(crate) struct Cli {
     #[arg(short, long, value_name = "NUM", default_value_t = 2)]
     length: usize,
 
+    /// Seed of the expression generation [default: random]
+    #[arg(short, long, value_name = "NUM")]
+    seed: Option<u64>,
+
+    /// Enumerate every combination of expressions for each EXPRE! slot instead of a random one
+    #[arg(long)]
+    exhaustive: bool,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Tools to be evaluated: adapters in `tools.yaml`, presets (miri, clippy, rustc, run), or executables
+    #[arg(required = true, num_args = 1..)]
+    tools: Vec<String>,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+
+    /// Evaluate the variants of a corpus written by `generate` instead of generating them; the
+    /// configuration directory is then only used for `tools.yaml`
+    ///
+    /// A corpus holds the variants generated from the same seed, length and depth, including the
+    /// descendants a run skips below the variants no tool detects robustly.
+    #[arg(long, value_name = "DIR")]
+    corpus: Option<PathBuf>,
+
+    /// Output path
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+
+    /// Exclude the variants whose behaviour under an oracle disagrees with their POS/NEG labels;
+    /// `[<kind>=]<tool>`, e.g. `panic=run`, where a bare tool judges every kind (ub, panic, leak)
+    /// it can observe and no other oracle is given for
+    #[arg(long, value_name = "[KIND=]TOOL")]
+    oracle: Vec<String>,
+
+    /// YAML file of the variants each tool is known to miss or false-alarm on, matched by their
+    /// sources whatever their number; the run only reports its differences, and exits with
+    /// status 3 on new misses or false alarms
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Overwrite the baseline with the misses and false alarms of this run
+    #[arg(long, requires = "baseline")]
+    update_baseline: bool,
+
+    /// Also write the verdicts as JUnit XML (`EvalResults.xml` of each tool)
+    #[arg(long)]
+    junit: bool,
+
+    /// Also write the expected and reported findings as SARIF (`EvalResults.sarif` of each tool)
+    #[arg(long)]
+    sarif: bool,
+
+    /// Minimize the variants on which a tool misses a bug it detects in their parent
+    #[arg(long)]
+    minimize: bool,
+
+    /// Keep the harnesses and their build artifacts after the run
+    #[arg(long)]
+    keep_harness: bool,
+}
+
+#[derive(Args)]
+struct LimitArgs {
+    /// Wall-clock timeout of each tool invocation
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Memory limit of each tool invocation
+    #[arg(long, value_name = "MiB")]
+    memory: Option<u64>,
+
+    /// CPU time limit of each tool invocation
+    #[arg(long, value_name = "SECS")]
+    cpu: Option<u64>,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Reference oracles as `[<kind>=]<tool>`, with an adapter in `tools.yaml`, a preset (miri,
+    /// run), or an executable; a bare tool judges every kind (ub, panic, leak) it can observe and
+    /// no other oracle is given for, and the variants of a kind without an oracle are inconclusive
+    #[arg(long, value_name = "[KIND=]TOOL", default_value = "miri")]
+    oracle: Vec<String>,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+
+    /// Verify the variants of a corpus written by `generate` instead of generating them
+    #[arg(long, value_name = "DIR")]
+    corpus: Option<PathBuf>,
+
     /// Output path
     #[arg(short, long, value_name = "DIR")]
     output: Option<PathBuf>,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+
+    /// Keep the harnesses and their build artifacts after the run
+    #[arg(long)]
+    keep_harness: bool,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    generation: GenerationArgs,
+
+    /// Output path of the corpus
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Configuration file
+    #[arg(short, long, value_name = "DIR")]
+    config: Option<PathBuf>,
+
+    /// Files to check, named `testcases*.yaml`, `expressions*.yaml` or `tools*.yaml` [default: the ones in the configuration directory]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Output path of the earlier run
+    before: PathBuf,
+
+    /// Output path of the later run
+    after: PathBuf,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = DiffFormat::Text)]
+    format: DiffFormat,
+
+    /// Number of regressions (TP -> FN, TN -> FP) tolerated before exiting with failure
+    #[arg(short, long, value_name = "NUM", default_value_t = 0)]
+    threshold: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiffFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+impl From<DiffFormat> for Format {
+    fn from(format: DiffFormat) -> Self {
+        match format {
+            DiffFormat::Text => Format::Text,
+            DiffFormat::Json => Format::Json,
+            DiffFormat::Markdown => Format::Markdown,
+        }
+    }
+}
+
+impl LimitArgs {
+    fn limits(&self) -> Limits {
+        Limits {
+            timeout: self.timeout,
+            memory: self.memory,
+            cpu: self.cpu,
+        }
+    }
+}
+
+impl GenerationArgs {
+    fn selection(&self) -> Selection {
+        Selection {
+            indices: self.indices.clone(),
+            ids: self.ids.clone(),
+            tags: self.tags.clone(),
+            features: self.features.clone(),
+            ubs: self.ubs.clone(),
+        }
+    }
+
+    /// Options of the generation, with the defaults of the evaluation
+    fn options(&self) -> Options {
+        Options {
+            length: self.length,
+            depth: self.depth,
+            seed: self.seed.unwrap_or_else(rand::random),
+            exhaustive: self.exhaustive,
+            ..Default::default()
+        }
+    }
 }
 
 impl Cli {
     pub(crate) fn main(self) {
         let current_dir = std::env::current_dir().unwrap();
-        let output = self.output.unwrap_or(current_dir.join("output"));
-        let config = self.config.unwrap_or(current_dir.join("config"));
-        Evaluator::new(self.tool, config, self.indices, self.length, self.depth, output).main();
+        match self.command {
+            Commands::Run(args) => {
+                let output = args.output.unwrap_or(current_dir.join("output"));
+                let generation = args.generation;
+                let config = generation.config.clone();
+                let config = config.unwrap_or(current_dir.join("config"));
+                let options = Options {
+                    corpus: args.corpus,
+                    limits: args.limits.limits(),
+                    minimize: args.minimize,
+                    keep_harness: args.keep_harness,
+                    oracle: args.oracle,
+                    baseline: args.baseline,
+                    update_baseline: args.update_baseline,
+                    junit: args.junit,
+                    sarif: args.sarif,
+                    ..generation.options()
+                };
+                let evaluator =
+                    Evaluator::new(args.tools, config, generation.selection(), output, options);
+                if !evaluator.main() {
+                    std::process::exit(REGRESSION_STATUS);
+                }
+            }
+            Commands::Generate(args) => {
+                let output = args.output.unwrap_or(current_dir.join("corpus"));
+                let generation = args.generation;
+                let config = generation.config.clone();
+                let config = config.unwrap_or(current_dir.join("config"));
+                let options = generation.options();
+                eval::generate(config, generation.selection(), output, options);
+            }
+            Commands::Verify(args) => {
+                let output = args.output.unwrap_or(current_dir.join("output"));
+                let generation = args.generation;
+                let config = generation.config.clone();
+                let config = config.unwrap_or(current_dir.join("config"));
+                let options = Options {
+                    corpus: args.corpus,
+                    limits: args.limits.limits(),
+                    keep_harness: args.keep_harness,
+                    oracle: args.oracle,
+                    ..generation.options()
+                };
+                let evaluator =
+                    Evaluator::new(vec![], config, generation.selection(), output, options);
+                if !evaluator.verify() {
+                    std::process::exit(1);
+                }
+            }
+            Commands::Validate(args) => {
+                let config = args.config.unwrap_or(current_dir.join("config"));
+                let files = match args.files {
+                    files if files.is_empty() => {
+                        ["testcases.yaml", "expressions.yaml", "tools.yaml"]
+                            .iter()
+                            .map(|name| config.join(name))
+                            .filter(|path| path.exists())
+                            .collect()
+                    }
+                    files => files,
+                };
+                if !eval::validate(&files) {
+                    std::process::exit(1);
+                }
+            }
+            Commands::Diff(args) => {
+                let format = args.format.into();
+                if !eval::diff(&args.before, &args.after, format, args.threshold) {
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
@@ -4,10 +4,18 @@ use std::{
 };
 
 use log::info;
+use rand::Rng;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::{Expr, Exprs, Program};
+use crate::{adapter::Finding, harness::Manifest, Expr, Exprs, Program};
+
+/// Marker of the line where the POS program misbehaves
+pub(crate) const BAD_SINK: &str = "// BAD SINK";
+/// Marker of the line where the NEG program is sound
+pub(crate) const GOOD_SINK: &str = "// GOOD SINK";
+/// Legacy marker used by POS programs of `testcases-90.yaml`
+pub(crate) const SINK: &str = "// SINK";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Testcases(Vec<Testcase>);
@@ -17,9 +25,60 @@ impl Testcases {
     where
         P: AsRef<Path>,
     {
-        info!("Testcases from file: {}", path.as_ref().display());
+        let path = path.as_ref();
+        info!("Testcases from file: {}", path.display());
+        let base = path.parent().unwrap_or(Path::new("."));
         let cnt = std::fs::read_to_string(path).expect("File not found");
-        serde_yaml::from_str(&cnt).expect("File Content Format Error")
+        let testcases = Testcases::parse(&cnt, base).unwrap_or_else(|err| {
+            panic!("File Content Format Error: {}: {}", path.display(), err)
+        });
+        let mut ids = std::collections::HashSet::new();
+        if let Some(testcase) = testcases.iter().find(|t| !ids.insert(&t.id)) {
+            panic!("Duplicate testcase ID: {}", testcase.id);
+        }
+        testcases
+    }
+
+    /// Parse the content of a testcases file, with dependency paths relative to `base`
+    pub(crate) fn parse(cnt: &str, base: &Path) -> Result<Self, serde_yaml::Error> {
+        let mut testcases: Testcases = serde_yaml::from_str(cnt)?;
+        for (testcase, section) in testcases.iter_mut().zip(Testcases::sections(cnt)) {
+            testcase.kind.get_or_insert(Kind::of_section(&section));
+            testcase.section = section;
+            testcase.manifest.resolve(base);
+        }
+        // 未指定 ID 的 testcase 以位置编号
+        for (idx, testcase) in testcases.iter_mut().enumerate() {
+            if testcase.id.is_empty() {
+                testcase.id = format!("testcase-{:03}", idx);
+            }
+        }
+        Ok(testcases)
+    }
+
+    /// Section of each testcase, from the `#`, `##` and `###` headings preceding it
+    ///
+    /// The top-level heading (e.g. `SP-PoC`) is only used when there are no sub-headings, so
+    /// the sections read `Box`, `Primitive/pointer` or `Panic-PoC`.
+    fn sections(cnt: &str) -> Vec<String> {
+        let mut headings: Vec<&str> = Vec::new();
+        let mut sections = Vec::new();
+        for line in cnt.lines() {
+            let level = line.chars().take_while(|&c| c == '#').count();
+            if level > 0 && line[level..].starts_with(' ') {
+                headings.truncate(level - 1);
+                headings.push(line[level..].trim());
+            } else if line.starts_with("- ") {
+                // 新的 testcase
+                let section = match headings.len() {
+                    0 => String::new(),
+                    1 => headings[0].to_string(),
+                    _ => headings[1..].join("/"),
+                };
+                sections.push(section);
+            }
+        }
+        sections
     }
 }
 
@@ -37,10 +96,19 @@ impl DerefMut for Testcases {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Testcase {
+    /// Stable ID, independent of the position in the YAML file
+    #[serde(default)]
+    id: String,
     #[serde(rename = "description")]
     desc: String,
+    /// Section of the YAML file, filled by [`Testcases::from_file`]
+    #[serde(default)]
+    section: String,
+    /// Kind of the bug, defaulting to `panic` in the `Panic-PoC` section and `ub` elsewhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<Kind>,
 
     tags: Vec<String>,
 
@@ -53,17 +121,141 @@ pub(crate) struct Testcase {
     pos: Case, // Positive Case
     #[serde(rename = "NEG")]
     neg: Case, // Negative Case
+    /// Edition, dependencies, features and profiles of the harness
+    #[serde(default)]
+    manifest: Manifest,
 }
 
 impl Testcase {
-    pub(crate) fn into_programs(&self, expr: &String) -> (Program, Program) {
-        let pos = Program::new(self.pos.nest(expr), "".to_string());
-        let neg = Program::new(self.neg.nest(expr), "".to_string());
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn description(&self) -> &str {
+        &self.desc
+    }
+
+    pub(crate) fn kind(&self) -> Kind {
+        self.kind.unwrap_or_default()
+    }
+
+    pub(crate) fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub(crate) fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    pub(crate) fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// The POS and NEG cases, with their YAML keys
+    pub(crate) fn cases(&self) -> [(&'static str, &Case); 2] {
+        [("POS", &self.pos), ("NEG", &self.neg)]
+    }
+
+    /// Categories of the testcase, as `(kind, name)`: the `SP(...)`, `UB(...)` and `TY(...)`
+    /// tags, the section and the kind of the bug
+    pub(crate) fn categories(&self) -> Vec<(&str, &str)> {
+        let mut categories: Vec<_> = self
+            .tags
+            .iter()
+            .filter_map(|tag| {
+                let (kind, name) = tag.strip_suffix(')')?.split_once('(')?;
+                ["SP", "UB", "TY"]
+                    .contains(&kind)
+                    .then_some((kind, name))
+            })
+            .collect();
+        categories.push(("Section", &self.section));
+        categories.push(("Kind", self.kind().name()));
+        categories
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn into_programs(&self, expr: &Expr, seed: u64) -> (Program, Program) {
+        let metadata = self.metadata(expr, seed);
+        let (code, sinks) = self.pos.nest_with_sinks(&expr.code, &[BAD_SINK, SINK]);
+        let pos = Program::new(code, metadata.clone(), sinks).of_testcase(self);
+        let (code, sinks) = self.neg.nest_with_sinks(&expr.code, &[GOOD_SINK]);
+        let neg = Program::new(code, metadata, sinks).of_testcase(self);
         (pos, neg)
     }
+
+    /// Comment header describing the testcase and the lineage of `expr`
+    fn metadata(&self, expr: &Expr, seed: u64) -> String {
+        let mut metadata = format!("// Testcase: {} - {}\n", self.id, self.desc);
+        metadata += &format!("// Tags: {}\n", self.tags.join(", "));
+        metadata += &format!(
+            "// Variant: {} (length {}, depth {})\n",
+            expr.num, expr.length, expr.depth
+        );
+        metadata += &format!("// Seed: {}\n", seed);
+        if !expr.metadata.is_empty() {
+            metadata += "// Flows (from the innermost):\n";
+            metadata += &expr.metadata;
+        }
+        metadata += "\n";
+        metadata
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Kind of the bug of a testcase, which decides the findings counted as detecting it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    /// Undefined Behavior, reported on a SINK line
+    #[default]
+    Ub,
+    /// Panic, reported where it is raised rather than on the SINK line
+    Panic,
+    /// Memory leak, reported where the memory is allocated rather than on the SINK line
+    Leak,
+}
+
+impl Kind {
+    pub(crate) const ALL: [Kind; 3] = [Kind::Ub, Kind::Panic, Kind::Leak];
+
+    fn of_section(section: &str) -> Self {
+        match section {
+            "Panic-PoC" => Kind::Panic,
+            _ => Kind::Ub,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Kind::Ub => "ub",
+            Kind::Panic => "panic",
+            Kind::Leak => "leak",
+        }
+    }
+
+    /// Check if `finding` is about a bug of this kind, wherever it is located
+    pub(crate) fn concerns(&self, finding: &Finding) -> bool {
+        let pattern = match self {
+            Kind::Ub => return true,
+            Kind::Panic => r"(?i)panic|unwrap|overflow",
+            Kind::Leak => r"(?i)leak",
+        };
+        let re = Regex::new(pattern).unwrap();
+        let kind = finding.kind.as_deref().unwrap_or_default();
+        re.is_match(kind) || re.is_match(&finding.message)
+    }
+
+    /// Check if `finding` detects a bug of this kind in a program with `sinks`
+    pub(crate) fn detects(&self, finding: &Finding, sinks: &[usize]) -> bool {
+        match self {
+            Kind::Ub => finding.hits(sinks),
+            // 位置不限于 SINK, 但须位于 main.rs
+            Kind::Panic | Kind::Leak => self.concerns(finding) && finding.hits(&[]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Case {
     #[serde(rename = "source")]
     src: String,
@@ -71,11 +263,37 @@ pub(crate) struct Case {
 }
 
 impl Case {
+    pub(crate) fn code(&self) -> &str {
+        &self.code
+    }
+
     /// Nest `expr` to `self.src`, and then nest `self.src` to `self.code`
-    pub(crate) fn nest(&self, expr: &String) -> String {
+    pub(crate) fn nest(&self, expr: &str) -> String {
         let source = format!("{{\n{}\n}}", expr.replace("SOURCE!()", &self.src));
         self.code.replace("SOURCE!()", source.trim())
     }
+
+    /// Same as [`Case::nest`], but also returns the 1-based lines of the nested program
+    /// carrying one of `markers`.
+    ///
+    /// The sink lines are tracked through the substitution: every line of `self.code` after a
+    /// `SOURCE!()` is shifted by the number of lines the nested source spans.
+    pub(crate) fn nest_with_sinks(&self, expr: &str, markers: &[&str]) -> (String, Vec<usize>) {
+        let code = self.nest(expr);
+        let source = format!("{{\n{}\n}}", expr.replace("SOURCE!()", &self.src));
+        let shift = source.trim().lines().count().saturating_sub(1);
+
+        let mut sinks = Vec::new();
+        let mut offset = 0;
+        for (line, text) in self.code.lines().enumerate() {
+            // 与 SOURCE!() 同行的 SINK 位于展开后的最后一行
+            offset += text.matches("SOURCE!()").count() * shift;
+            if markers.iter().any(|marker| text.contains(marker)) {
+                sinks.push(line + 1 + offset);
+            }
+        }
+        (code, sinks)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -86,9 +304,22 @@ impl Flows {
     where
         P: AsRef<Path>,
     {
-        info!("Flows from file: {}", path.as_ref().display());
+        let path = path.as_ref();
+        info!("Flows from file: {}", path.display());
         let cnt = std::fs::read_to_string(path).expect("File not found");
-        serde_yaml::from_str(&cnt).expect("File Content Format Error")
+        Flows::parse(&cnt).unwrap_or_else(|err| {
+            panic!("File Content Format Error: {}: {}", path.display(), err)
+        })
+    }
+
+    /// Parse the content of a flows file
+    pub(crate) fn parse(cnt: &str) -> Result<Self, serde_yaml::Error> {
+        let mut flows: Flows = serde_yaml::from_str(cnt)?;
+        // flow 名称可能重复, 以位置标识
+        for (index, flow) in flows.iter_mut().enumerate() {
+            flow.index = index;
+        }
+        Ok(flows)
     }
 }
 
@@ -110,29 +341,168 @@ impl DerefMut for Flows {
 pub(crate) struct Flow {
     name: String,
     code: String,
+    /// Position in `expressions.yaml`
+    #[serde(skip)]
+    index: usize,
+}
+
+/// How an expression is built from `SOURCE!()`: the flows it is nested in, from the innermost,
+/// each with the recipes of its `EXPRE!()` fillings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Recipe(Vec<Step>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Step {
+    flow: usize,
+    fillings: Vec<Recipe>,
+}
+
+impl Recipe {
+    /// Rebuild the expression, numbered `num`
+    pub(crate) fn build(&self, num: usize, flows: &Flows, case: &Testcase) -> Expr {
+        let mut expr = Expr::source();
+        for step in self.0.iter() {
+            let fillings: Vec<_> = step
+                .fillings
+                .iter()
+                .map(|filling| filling.build(0, flows, case))
+                .collect();
+            let fillings: Vec<_> = fillings.iter().collect();
+            expr = flows[step.flow].fill(num, &expr, &fillings, case);
+        }
+        expr
+    }
+
+    /// Recipes one step simpler: without one of the flows, or with one of the `EXPRE!()`
+    /// fillings replaced by `SOURCE!()`
+    pub(crate) fn reductions(&self) -> Vec<Recipe> {
+        let mut reductions = Vec::new();
+        for i in (0..self.0.len()).rev() {
+            let mut recipe = self.clone();
+            recipe.0.remove(i);
+            reductions.push(recipe);
+        }
+        for (i, step) in self.0.iter().enumerate() {
+            for (j, filling) in step.fillings.iter().enumerate() {
+                if !filling.0.is_empty() {
+                    let mut recipe = self.clone();
+                    recipe.0[i].fillings[j] = Recipe::default();
+                    reductions.push(recipe);
+                }
+            }
+        }
+        reductions
+    }
+
+    /// Number of flows, including the ones of the fillings
+    pub(crate) fn size(&self) -> usize {
+        self.0
+            .iter()
+            .map(|step| 1 + step.fillings.iter().map(Recipe::size).sum::<usize>())
+            .sum()
+    }
 }
 
 impl Flow {
-    pub(crate) fn into_expr(&self, num: usize, src: &Expr, exprs: &Exprs, case: &Testcase) -> Expr {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn code(&self) -> &str {
+        &self.code
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn into_expr<R: Rng>(
+        &self,
+        num: usize,
+        src: &Expr,
+        exprs: &Exprs,
+        case: &Testcase,
+        rng: &mut R,
+    ) -> Expr {
+        // 每个 EXPRE!() 随机选取一个 expr
+        let fillings: Vec<_> = (0..self.slots())
+            .map(|_| exprs.random_expr(rng).unwrap())
+            .collect();
+        self.fill(num, src, &fillings, case)
+    }
+
+    /// Every combination of `exprs` for the `EXPRE!()` slots, numbered from `num`
+    ///
+    /// The combinations are generated lazily, in lexicographic order of the indices in `exprs`.
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn into_exprs<'a>(
+        &'a self,
+        num: usize,
+        src: &'a Expr,
+        exprs: &'a [Expr],
+        case: &'a Testcase,
+    ) -> impl Iterator<Item = Expr> + 'a {
+        let slots = self.slots();
+        let total = exprs
+            .len()
+            .checked_pow(slots as u32)
+            .expect("Too many combinations of EXPRE!() fillings");
+        (0..total).map(move |i| {
+            // 将 i 解码为 slots 位 exprs.len() 进制数
+            let mut rest = i;
+            let mut fillings = vec![&exprs[0]; slots];
+            for filling in fillings.iter_mut().rev() {
+                *filling = &exprs[rest % exprs.len()];
+                rest /= exprs.len();
+            }
+            self.fill(num + i, src, &fillings, case)
+        })
+    }
+
+    /// Number of `EXPRE!()` slots
+    pub(crate) fn slots(&self) -> usize {
+        Regex::new(r"EXPRE!\((.*?)\)")
+            .unwrap()
+            .find_iter(&self.code)
+            .count()
+    }
+
+    /// Nest `src` into the flow, and fill the i-th `EXPRE!()` with `fillings[i]`
+    fn fill(&self, num: usize, src: &Expr, fillings: &[&Expr], case: &Testcase) -> Expr {
         let mut code = self.code.replacen("SOURCE!()", &format!("{{\n{}\n}}", &src.code), 1); // SOURCE!() 替换
         code = code.replace("TYPE!()", &case.ty); // TYPE!() 替换
         code = code.replace("VALUE!()", &format!("{{\n{}\n}}", case.val)); // VALUE!() 替换
         code = code.replace("COND!()", "true"); // COND!() 替换
         let length = src.length + 1;
-        let mut depth = src.depth; // Todo: 根据 exprs 选取的变动
+        let mut depth = src.depth;
 
         // EXPR!() 替换
         let re = Regex::new(r"EXPRE!\((.*?)\)").unwrap();
+        let mut slots = fillings.iter();
         code = re.replace_all(&code, |caps: &regex::Captures| {
             // 提取括号内的内容
             let param = caps[1].to_string();
-            let expr = exprs.random_expr().unwrap();
+            let expr = slots.next().unwrap();
             depth = std::cmp::max(depth, expr.depth + 1);
             format!("{{\n{}\n}}", expr.fill_source(&param)) // 用指定的替换字符串
         }).to_string();
 
-        let metadata = src.metadata.clone();
-        Expr::new(num, code, length, depth, metadata)
+        let mut chain = src.chain.clone();
+        chain.push(self.name.clone());
+        // 记录 flow 及其 EXPRE!() 的填充
+        let mut metadata = format!("//   {}. {}", chain.len(), self.name);
+        for (i, expr) in fillings.iter().enumerate() {
+            let sep = if i == 0 { " <- EXPRE!: " } else { ", " };
+            let flows = match expr.chain.is_empty() {
+                true => String::from("SOURCE!()"),
+                false => expr.chain.join(" > "),
+            };
+            metadata += &format!("{}{} ({})", sep, expr.num, flows);
+        }
+        let metadata = format!("{}{}\n", src.metadata, metadata);
+        let mut recipe = src.recipe.clone();
+        recipe.0.push(Step {
+            flow: self.index,
+            fillings: fillings.iter().map(|expr| expr.recipe.clone()).collect(),
+        });
+        Expr::new(num, code, length, depth, metadata, chain, recipe)
     }
 }
 
@@ -144,7 +514,35 @@ mod test {
     #[test]
     fn test_testcases_from_file() {
         let testcases = Testcases::from_file("config/testcases.yaml");
-        assert_eq!(testcases[0].ty, String::from("Layout"));
+        assert_eq!(testcases[0].ty, String::from("*mut u8"));
+        assert_eq!(testcases[0].id(), "sp-system-01");
+        assert_eq!(testcases[0].section, "System");
+        let last = testcases.last().unwrap();
+        assert_eq!(last.section, "Panic-PoC");
+        assert!(testcases
+            .iter()
+            .any(|t| t.section == "Primitive/pointer"));
+        assert_eq!(
+            testcases[0].categories(),
+            [
+                ("SP", "Initialized"),
+                ("UB", "Producing an invalid value"),
+                ("TY", "Uninit"),
+                ("Section", "System"),
+                ("Kind", "ub")
+            ]
+        );
+        assert_eq!(last.kind(), Kind::Panic);
+
+        let panic = Finding {
+            kind: Some(String::from("panic")),
+            file: Some(String::from("src/main.rs")),
+            line: Some(9),
+            message: String::from("called `Option::unwrap()` on a `None` value"),
+        };
+        assert!(Kind::Panic.detects(&panic, &[11]));
+        assert!(!Kind::Leak.detects(&panic, &[11]));
+        assert!(!Kind::Ub.detects(&panic, &[11]));
     }
 
     #[test]
@@ -179,4 +577,50 @@ mod test {
 
         println!("{}", case.nest(&expr));
     }
+
+    #[test]
+    fn test_seeded_into_expr() {
+        use rand::{rngs::StdRng, SeedableRng as _};
+
+        let testcases = Testcases::from_file("config/testcases.yaml");
+        let flows = Flows::from_file("config/expressions.yaml");
+        let generate = |seed: u64| -> Vec<String> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut exprs = Exprs::new();
+            exprs.push(Expr::source());
+            for (num, flow) in flows.iter().enumerate() {
+                let expr = flow.into_expr(num, &Expr::source(), &exprs, &testcases[0], &mut rng);
+                exprs.push(expr);
+            }
+            exprs.iter().map(|expr| expr.code.clone()).collect()
+        };
+        assert_eq!(generate(42), generate(42));
+    }
+
+    #[test]
+    fn test_nest_with_sinks() {
+        let testcases = Testcases::from_file("config/testcases.yaml");
+        let flows = Flows::from_file("config/expressions.yaml");
+        let mut exprs = Exprs::new();
+        exprs.push(Expr::source());
+        for testcase in testcases.iter() {
+            for flow in flows.iter() {
+                let mut rng = rand::thread_rng();
+                let expr = flow.into_expr(1, &Expr::source(), &exprs, testcase, &mut rng);
+                let (pos, neg) = testcase.into_programs(&expr, 42);
+                for (program, marker) in [(pos, BAD_SINK), (neg, GOOD_SINK)] {
+                    let code = program.merge();
+                    // 程序以描述变体的注释开头
+                    assert!(code.starts_with(&format!("// Testcase: {}", testcase.id)));
+                    assert!(code.contains(&format!("//   1. {}", flow.name)));
+                    assert!(code.contains("// Seed: 42"));
+                    let lines: Vec<_> = code.lines().collect();
+                    assert!(!program.sinks().is_empty());
+                    for line in program.sinks() {
+                        assert!(lines[line - 1].contains(marker));
+                    }
+                }
+            }
+        }
+    }
 }
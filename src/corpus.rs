@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Testcase, generator::Variant, utils, Config, Expr, Program};
+
+/// Version of the corpus manifest, bumped on incompatible changes
+pub(crate) const CORPUS_VERSION: u32 = 1;
+
+/// A frozen set of variants, generated once and evaluated many times
+///
+/// The variants are written as `{id}/{num}/{POS,NEG}.rs` next to `corpus.json`. They are the
+/// variants of a live run with the same seed, length and depth, with the same numbers and sources.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Corpus {
+    version: u32,
+    seed: u64,
+    length: usize,
+    depth: usize,
+    exhaustive: bool,
+    pub(crate) testcases: Vec<CorpusTestcase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CorpusTestcase {
+    pub(crate) idx: usize,
+    pub(crate) testcase: Testcase,
+    pub(crate) variants: Vec<CorpusVariant>,
+}
+
+/// Lineage of a variant and its programs
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CorpusVariant {
+    pub(crate) num: String,
+    /// `None` for the original testcase
+    pub(crate) parent: Option<String>,
+    length: usize,
+    depth: usize,
+    /// Flows the source is nested in, from the innermost
+    #[serde(default)]
+    pub(crate) chain: Vec<String>,
+    pos: CorpusProgram,
+    neg: CorpusProgram,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CorpusProgram {
+    /// Path relative to the corpus directory
+    path: PathBuf,
+    sinks: Vec<usize>,
+}
+
+impl CorpusProgram {
+    fn new(path: PathBuf, program: &Program) -> Self {
+        CorpusProgram {
+            path,
+            sinks: program.sinks().to_vec(),
+        }
+    }
+
+    fn load(&self, dir: &Path, testcase: &Testcase) -> Program {
+        let code = std::fs::read_to_string(dir.join(&self.path)).expect("File not found");
+        Program::new(code, String::new(), self.sinks.clone()).of_testcase(testcase)
+    }
+}
+
+impl CorpusVariant {
+    fn new(
+        dir: &Path,
+        parent: Option<String>,
+        expr: &Expr,
+        testcase: &Testcase,
+        seed: u64,
+    ) -> Self {
+        let (pos, neg) = testcase.into_programs(expr, seed);
+        let path = PathBuf::from(testcase.id()).join(&expr.num);
+        utils::write(dir.join(&path), (&pos, &neg));
+        CorpusVariant {
+            num: expr.num.clone(),
+            parent,
+            length: expr.length,
+            depth: expr.depth,
+            chain: expr.chain.clone(),
+            pos: CorpusProgram::new(path.join("POS.rs"), &pos),
+            neg: CorpusProgram::new(path.join("NEG.rs"), &neg),
+        }
+    }
+
+    /// Sequence length and nesting depth of the expression
+    pub(crate) fn shape(&self) -> (usize, usize) {
+        (self.length, self.depth)
+    }
+
+    /// Load the POS and NEG programs of `testcase` from the corpus directory `dir`
+    pub(crate) fn programs(&self, dir: &Path, testcase: &Testcase) -> (Program, Program) {
+        (self.pos.load(dir, testcase), self.neg.load(dir, testcase))
+    }
+}
+
+impl Corpus {
+    /// Generate the variants of `targets` into `dir`, independently of any tool
+    pub(crate) fn generate(config: &Config, targets: &[usize], dir: &Path) -> Self {
+        let testcases = targets
+            .par_iter()
+            .map(|&idx| {
+                let testcase = &config.testcases[idx];
+                let mut variants = vec![CorpusVariant::new(
+                    dir,
+                    None,
+                    &Expr::source(),
+                    testcase,
+                    config.seed,
+                )];
+                let mut visit = |Variant { parent, expr }| {
+                    variants.push(CorpusVariant::new(
+                        dir,
+                        Some(parent),
+                        &expr,
+                        testcase,
+                        config.seed,
+                    ));
+                };
+                let generator = config.generator(testcase);
+                match config.exhaustive {
+                    true => generator.exhaustive(&mut visit),
+                    false => generator.random(&mut config.rng(testcase), &mut visit),
+                }
+                info!("Generate {} variants of {}", variants.len(), testcase.id());
+                CorpusTestcase {
+                    idx,
+                    testcase: testcase.clone(),
+                    variants,
+                }
+            })
+            .collect();
+        let corpus = Corpus {
+            version: CORPUS_VERSION,
+            seed: config.seed,
+            length: config.length,
+            depth: config.depth,
+            exhaustive: config.exhaustive,
+            testcases,
+        };
+        let file = std::fs::File::create(dir.join("corpus.json")).expect("File creation failed");
+        serde_json::to_writer_pretty(file, &corpus).expect("Corpus serialization failed");
+        corpus
+    }
+
+    pub(crate) fn from_dir(dir: &Path) -> Self {
+        info!("Corpus from directory: {}", dir.display());
+        let file = std::fs::File::open(dir.join("corpus.json")).expect("File not found");
+        let corpus: Corpus = serde_json::from_reader(file).expect("File Content Format Error");
+        if corpus.version != CORPUS_VERSION {
+            panic!(
+                "Corpus version {} is not supported (expected {})",
+                corpus.version, CORPUS_VERSION
+            );
+        }
+        corpus
+    }
+
+    /// Testcase `idx` of the corpus
+    pub(crate) fn testcase(&self, idx: usize) -> &CorpusTestcase {
+        self.testcases.iter().find(|t| t.idx == idx).unwrap()
+    }
+
+    /// Seed the corpus was generated with
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate_corpus() {
+        let dir = PathBuf::from("./output/corpus");
+        let config = Config::new("config".into(), 2, 3, 42, false);
+        Corpus::generate(&config, &[0, 1], &dir);
+        let corpus = Corpus::from_dir(&dir);
+        assert_eq!(corpus.testcases.len(), 2);
+        assert_eq!(corpus.seed(), 42);
+
+        let variant = &corpus.testcases[1].variants[3];
+        assert_eq!(variant.parent.as_deref(), Some("0000-0-0"));
+        let testcase = &corpus.testcases[1].testcase;
+        let (pos, _) = variant.programs(&dir, testcase);
+        assert!(pos
+            .merge()
+            .lines()
+            .nth(pos.sinks()[0] - 1)
+            .unwrap()
+            .contains("BAD SINK"));
+
+        // 相同的种子生成相同的语料
+        let other = PathBuf::from("./output/corpus-again");
+        let again = Corpus::generate(&config, &[1], &other);
+        let (again, _) = again.testcases[0].variants[3].programs(&other, testcase);
+        assert_eq!(pos.merge(), again.merge());
+    }
+}
@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use tabled::Tabled;
+
+use crate::{percent, utils::escape, EvalRow, EvalSummary};
+
+/// Size of each panel of the SVG chart
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 320.0;
+/// Colours of the tools, in order
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+/// Verdicts of one tool on the variants of one nesting depth and sequence length
+#[derive(Tabled)]
+pub(crate) struct CurveReport {
+    #[tabled(rename = "工具")]
+    tool: String,
+    #[tabled(rename = "深度")]
+    depth: usize,
+    #[tabled(rename = "长度")]
+    length: usize,
+    #[tabled(rename = "变体")]
+    variants: usize,
+    #[tabled(rename = "TP")]
+    true_positive: usize,
+    #[tabled(rename = "FN")]
+    false_negative: usize,
+    #[tabled(rename = "FP")]
+    false_positive: usize,
+    #[tabled(rename = "TN")]
+    true_negative: usize,
+    #[tabled(rename = "检测率 (TP/(TP+FN))")]
+    detection: String,
+    #[tabled(rename = "鲁棒率 (RD/V)")]
+    robustness: String,
+}
+
+impl CurveReport {
+    /// One report per tool and bucket, by increasing depth and then length
+    pub(crate) fn report(tools: &[String], rows: &[&EvalRow]) -> Vec<Self> {
+        let buckets = buckets(tools.len(), rows, |row| (row.depth, row.length));
+        let mut reports = Vec::new();
+        for (i, tool) in tools.iter().enumerate() {
+            for (&(depth, length), summaries) in buckets.iter() {
+                let summary = &summaries[i];
+                reports.push(CurveReport {
+                    tool: tool.clone(),
+                    depth,
+                    length,
+                    variants: summary.variant_count,
+                    true_positive: summary.tp_count,
+                    false_negative: summary.fn_count,
+                    false_positive: summary.fp_count,
+                    true_negative: summary.tn_count,
+                    detection: percent(summary.recall()),
+                    robustness: percent(summary.robustness()),
+                });
+            }
+        }
+        reports
+    }
+}
+
+/// Summary of every tool on the variants of each bucket
+fn buckets<K, F>(tools: usize, rows: &[&EvalRow], key: F) -> BTreeMap<K, Vec<EvalSummary>>
+where
+    K: Ord,
+    F: Fn(&EvalRow) -> K,
+{
+    let mut buckets = BTreeMap::new();
+    for row in rows {
+        let summaries = buckets
+            .entry(key(row))
+            .or_insert_with(|| vec![EvalSummary::new(0, ""); tools]);
+        for (summary, res) in summaries.iter_mut().zip(&row.results) {
+            summary.count(res);
+        }
+    }
+    buckets
+}
+
+/// Line chart of the detection rate of every tool, by nesting depth and by sequence length
+pub(crate) fn svg(tools: &[String], rows: &[&EvalRow]) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"sans-serif\" font-size=\"12\">\n",
+        2.0 * WIDTH,
+        HEIGHT
+    );
+    svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+    let panels = [
+        ("nesting depth", buckets(tools.len(), rows, |row| row.depth)),
+        (
+            "sequence length",
+            buckets(tools.len(), rows, |row| row.length),
+        ),
+    ];
+    for (i, (label, buckets)) in panels.iter().enumerate() {
+        panel(&mut svg, i as f64 * WIDTH, label, tools.len(), buckets);
+    }
+    // 图例
+    for (i, tool) in tools.iter().enumerate() {
+        let (x, y) = (60.0 + i as f64 * 140.0, HEIGHT - 24.0);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{}\"/>\
+             <text x=\"{}\" y=\"{}\">{}</text>",
+            x,
+            y,
+            COLORS[i % COLORS.len()],
+            x + 18.0,
+            y + 10.0,
+            escape(tool)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draw the panel of `buckets` at the horizontal `offset`
+fn panel(
+    svg: &mut String,
+    offset: f64,
+    label: &str,
+    tools: usize,
+    buckets: &BTreeMap<usize, Vec<EvalSummary>>,
+) {
+    let (left, top) = (offset + 60.0, 40.0);
+    let (width, height) = (WIDTH - 80.0, HEIGHT - 120.0);
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"24\" text-anchor=\"middle\" font-size=\"14\">\
+         Detection rate by {}</text>",
+        left + width / 2.0,
+        label
+    );
+    for tick in 0..=4 {
+        let y = top + (1.0 - tick as f64 / 4.0) * height;
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"#ddd\"/>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}%</text>",
+            left,
+            left + width,
+            left - 8.0,
+            y + 4.0,
+            tick * 25
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<line x1=\"{left}\" y1=\"{top}\" x2=\"{left}\" y2=\"{bottom}\" stroke=\"black\"/>\
+         <line x1=\"{left}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"black\"/>\
+         <text x=\"{center}\" y=\"{label_y}\" text-anchor=\"middle\">{label}</text>",
+        bottom = top + height,
+        right = left + width,
+        center = left + width / 2.0,
+        label_y = top + height + 36.0,
+    );
+
+    let (Some(&min), Some(&max)) = (buckets.keys().next(), buckets.keys().last()) else {
+        return;
+    };
+    let x = |key: usize| match max - min {
+        0 => left + width / 2.0,
+        span => left + (key - min) as f64 / span as f64 * width,
+    };
+    for &key in buckets.keys() {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            x(key),
+            top + height + 18.0,
+            key
+        );
+    }
+    for i in 0..tools {
+        let color = COLORS[i % COLORS.len()];
+        // 无 POS 结论 (如全部无效) 的点不绘制
+        let points: Vec<(f64, f64)> = buckets
+            .iter()
+            .filter_map(|(&key, summaries)| {
+                let rate = summaries[i].recall()?;
+                Some((x(key), top + (1.0 - rate) * height))
+            })
+            .collect();
+        let polyline: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            polyline.join(" "),
+            color
+        );
+        for (x, y) in points {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"3\" fill=\"{}\"/>",
+                x, y, color
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EvalResult, EvalResults};
+
+    #[test]
+    fn test_curve() {
+        let row = |length, depth, pos| EvalRow {
+            length,
+            depth,
+            ..EvalRow::test("", None, &[], EvalResults(pos, EvalResult::TN))
+        };
+        let rows = [
+            row(0, 0, EvalResult::TP),
+            row(1, 0, EvalResult::TP),
+            row(1, 1, EvalResult::TP),
+            row(1, 1, EvalResult::FN),
+            row(2, 2, EvalResult::FN),
+        ];
+        let rows: Vec<_> = rows.iter().collect();
+        let tools = [String::from("a<b")];
+
+        let reports = CurveReport::report(&tools, &rows);
+        let buckets: Vec<_> = reports.iter().map(|r| (r.depth, r.length)).collect();
+        assert_eq!(buckets, [(0, 0), (0, 1), (1, 1), (2, 2)]);
+        assert_eq!(reports[2].detection, "50.0%");
+        assert_eq!(reports[3].robustness, "0.0%");
+
+        let svg = svg(&tools, &rows);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        // 深度 0, 1, 2 的检测率分别为 100%, 50%, 0%
+        assert!(svg.contains("points=\"60,40 260,140 460,240\""));
+        assert!(svg.contains("a&lt;b"));
+    }
+}
@@ -0,0 +1,457 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::utils;
+
+/// Output format of a [`Diff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Markdown,
+}
+
+/// Verdict changes between two evaluation runs
+#[derive(Debug, Serialize)]
+pub(crate) struct Diff {
+    before: PathBuf,
+    after: PathBuf,
+    regressions: usize,
+    fixes: usize,
+    /// Testcases evaluated by a tool in only one of the runs, as `tool/id`
+    only_before: Vec<String>,
+    only_after: Vec<String>,
+    /// Variants of the common testcases without a variant of the same content in the other run
+    unmatched: (usize, usize),
+    testcases: Vec<TestcaseChange>,
+    variants: Vec<VariantChange>,
+}
+
+/// Counts of `EvalSummary.csv` which changed for a testcase
+#[derive(Debug, Serialize)]
+struct TestcaseChange {
+    tool: String,
+    id: String,
+    counts: Vec<CountChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct CountChange {
+    column: String,
+    before: String,
+    after: String,
+}
+
+/// Verdict change of a variant node of `evalTree.json`
+#[derive(Debug, Serialize)]
+struct VariantChange {
+    tool: String,
+    id: String,
+    /// Number of the variant in the `after` run
+    variant: String,
+    flows: Vec<String>,
+    before: String,
+    after: String,
+    kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    /// TP -> FN on the POS program, or TN -> FP on the NEG program
+    Regression,
+    /// FN -> TP on the POS program, or FP -> TN on the NEG program
+    Fix,
+    /// Any other change, e.g. to an error or a timeout
+    Other,
+}
+
+impl ChangeKind {
+    fn of(before: &str, after: &str) -> Self {
+        let (before, after) = (verdicts(before), verdicts(after));
+        if (before.0 == "TP" && after.0 == "FN") || (before.1 == "TN" && after.1 == "FP") {
+            ChangeKind::Regression
+        } else if (before.0 == "FN" && after.0 == "TP") || (before.1 == "FP" && after.1 == "TN") {
+            ChangeKind::Fix
+        } else {
+            ChangeKind::Other
+        }
+    }
+}
+
+/// POS and NEG verdicts of a serialized `EvalResults`; the other results (errors, timeouts,
+/// invalid variants) apply to both programs
+fn verdicts(result: &str) -> (&str, &str) {
+    match result {
+        "True Positive & Negative" => ("TP", "TN"),
+        "False Positive" => ("TP", "FP"),
+        "False Positive & Negative" => ("FN", "FP"),
+        "False Negative" => ("FN", "TN"),
+        other => (other, other),
+    }
+}
+
+/// Short form of a serialized `EvalResults`, e.g. `TP/TN`
+fn short(result: &str) -> String {
+    match verdicts(result) {
+        (pos, neg) if pos == neg => pos.to_string(),
+        (pos, neg) => format!("{}/{}", pos, neg),
+    }
+}
+
+impl Diff {
+    /// Compare the output directories `before` and `after` of two runs
+    pub(crate) fn new(before: &Path, after: &Path) -> Self {
+        let mut diff = Diff {
+            before: before.to_path_buf(),
+            after: after.to_path_buf(),
+            regressions: 0,
+            fixes: 0,
+            only_before: Vec::new(),
+            only_after: Vec::new(),
+            unmatched: (0, 0),
+            testcases: Vec::new(),
+            variants: Vec::new(),
+        };
+        let (tools_before, tools_after) = (tools(before), tools(after));
+        for tool in tools_before
+            .iter()
+            .filter(|tool| tools_after.contains(tool))
+        {
+            diff.compare(tool);
+        }
+        diff.regressions = diff.count(ChangeKind::Regression);
+        diff.fixes = diff.count(ChangeKind::Fix);
+        diff
+    }
+
+    pub(crate) fn regressions(&self) -> usize {
+        self.regressions
+    }
+
+    fn count(&self, kind: ChangeKind) -> usize {
+        self.variants.iter().filter(|v| v.kind == kind).count()
+    }
+
+    fn compare(&mut self, tool: &str) {
+        let before = summaries(&self.before.join(tool).join("EvalSummary.csv"));
+        let after = summaries(&self.after.join(tool).join("EvalSummary.csv"));
+        for id in before.keys().filter(|id| !after.contains_key(*id)) {
+            self.only_before.push(format!("{}/{}", tool, id));
+        }
+        for id in after.keys().filter(|id| !before.contains_key(*id)) {
+            self.only_after.push(format!("{}/{}", tool, id));
+        }
+        for (id, counts) in before.iter() {
+            let Some(others) = after.get(id) else {
+                continue;
+            };
+            let changed: Vec<_> = counts
+                .iter()
+                .filter(|(column, before)| others.get(*column) != Some(before))
+                .map(|(column, before)| CountChange {
+                    column: column.clone(),
+                    before: before.clone(),
+                    after: others.get(column).cloned().unwrap_or_default(),
+                })
+                .collect();
+            if !changed.is_empty() {
+                self.testcases.push(TestcaseChange {
+                    tool: tool.to_string(),
+                    id: id.clone(),
+                    counts: changed,
+                });
+            }
+
+            let before = nodes(&self.before.join(tool).join(id).join("evalTree.json"));
+            let after = nodes(&self.after.join(tool).join(id).join("evalTree.json"));
+            self.unmatched.0 += before.keys().filter(|k| !after.contains_key(*k)).count();
+            self.unmatched.1 += after.keys().filter(|k| !before.contains_key(*k)).count();
+            for (key, (_, result, flows)) in before.iter() {
+                let Some((variant, other, _)) = after.get(key) else {
+                    continue;
+                };
+                if result != other {
+                    self.variants.push(VariantChange {
+                        tool: tool.to_string(),
+                        id: id.clone(),
+                        variant: variant.clone(),
+                        flows: flows.clone(),
+                        before: short(result),
+                        after: short(other),
+                        kind: ChangeKind::of(result, other),
+                    });
+                }
+            }
+        }
+    }
+
+    pub(crate) fn render(&self, format: Format) -> String {
+        match format {
+            Format::Json => serde_json::to_string_pretty(self).expect("Diff serialization failed"),
+            Format::Text => self.text(),
+            Format::Markdown => self.markdown(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = format!(
+            "{} -> {}\nRegressions: {}, fixes: {}, other changes: {}\n",
+            self.before.display(),
+            self.after.display(),
+            self.regressions,
+            self.fixes,
+            self.count(ChangeKind::Other)
+        );
+        for variant in self.variants.iter() {
+            // 根节点没有 flow
+            let flows = match variant.flows.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", variant.flows.join(" > ")),
+            };
+            let _ = writeln!(
+                text,
+                "{:?}: {} {} {}{}: {} -> {}",
+                variant.kind,
+                variant.tool,
+                variant.id,
+                variant.variant,
+                flows,
+                variant.before,
+                variant.after
+            );
+        }
+        for testcase in self.testcases.iter() {
+            let counts: Vec<_> = testcase
+                .counts
+                .iter()
+                .map(|c| format!("{} {} -> {}", c.column, c.before, c.after))
+                .collect();
+            let _ = writeln!(
+                text,
+                "{} {}: {}",
+                testcase.tool,
+                testcase.id,
+                counts.join(", ")
+            );
+        }
+        for testcase in self.only_before.iter() {
+            let _ = writeln!(text, "Only in {}: {}", self.before.display(), testcase);
+        }
+        for testcase in self.only_after.iter() {
+            let _ = writeln!(text, "Only in {}: {}", self.after.display(), testcase);
+        }
+        if self.unmatched != (0, 0) {
+            let _ = writeln!(
+                text,
+                "Variants without a match of the same content: {} before, {} after",
+                self.unmatched.0, self.unmatched.1
+            );
+        }
+        text
+    }
+
+    fn markdown(&self) -> String {
+        let mut md = format!(
+            "# `{}` → `{}`\n\n**{}** regressions, **{}** fixes, **{}** other changes\n",
+            self.before.display(),
+            self.after.display(),
+            self.regressions,
+            self.fixes,
+            self.count(ChangeKind::Other)
+        );
+        if !self.variants.is_empty() {
+            md.push_str(
+                "\n## Variants\n\n| Change | Tool | ID | Variant | Flows | Before | After |\n",
+            );
+            md.push_str("|---|---|---|---|---|---|---|\n");
+            for v in self.variants.iter() {
+                let _ = writeln!(
+                    md,
+                    "| {:?} | {} | {} | {} | {} | {} | {} |",
+                    v.kind,
+                    v.tool,
+                    v.id,
+                    v.variant,
+                    v.flows.join(" > "),
+                    v.before,
+                    v.after
+                );
+            }
+        }
+        if !self.testcases.is_empty() {
+            md.push_str("\n## Testcases\n\n| Tool | ID | Counts |\n|---|---|---|\n");
+            for t in self.testcases.iter() {
+                let counts: Vec<_> = t
+                    .counts
+                    .iter()
+                    .map(|c| format!("{} {} → {}", c.column, c.before, c.after))
+                    .collect();
+                let _ = writeln!(md, "| {} | {} | {} |", t.tool, t.id, counts.join(", "));
+            }
+        }
+        for (dir, testcases) in [
+            (&self.before, &self.only_before),
+            (&self.after, &self.only_after),
+        ] {
+            if !testcases.is_empty() {
+                let _ = writeln!(
+                    md,
+                    "\nOnly in `{}`: {}",
+                    dir.display(),
+                    testcases.join(", ")
+                );
+            }
+        }
+        if self.unmatched != (0, 0) {
+            let _ = writeln!(
+                md,
+                "\nVariants without a match of the same content: {} before, {} after",
+                self.unmatched.0, self.unmatched.1
+            );
+        }
+        md
+    }
+}
+
+/// Tools evaluated in the run `dir`: its subdirectories with an `EvalSummary.csv`
+fn tools(dir: &Path) -> Vec<String> {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Failed to read {}: {}", dir.display(), err));
+    let mut tools: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("EvalSummary.csv").exists())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    tools.sort();
+    tools
+}
+
+/// Counts of each testcase in `EvalSummary.csv`, by ID
+fn summaries(path: &Path) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut reader = csv::Reader::from_path(path)
+        .unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+    let headers = reader.headers().expect("Invalid EvalSummary.csv").clone();
+    let mut summaries = BTreeMap::new();
+    for record in reader.records() {
+        let record = record.expect("Invalid EvalSummary.csv");
+        let row: BTreeMap<String, String> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(header, field)| (header.to_string(), field.to_string()))
+            .collect();
+        // 编号随选择变化, 以 ID 标识 testcase
+        let mut counts = row.clone();
+        counts.remove("编号");
+        let id = counts.remove("ID").unwrap_or_else(|| row["编号"].clone());
+        summaries.insert(id, counts);
+    }
+    summaries
+}
+
+/// Number, result and flows of every node of `evalTree.json`, by the fingerprint of its sources
+///
+/// Variant numbers depend on the seed and on the variants skipped by a run, so the nodes of two
+/// runs are matched by content; the number is only the key of a node whose sources are missing.
+fn nodes(path: &Path) -> BTreeMap<String, (String, String, Vec<String>)> {
+    fn walk(dir: &Path, node: &Value, nodes: &mut BTreeMap<String, (String, String, Vec<String>)>) {
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        let flows = node["flows"]
+            .as_array()
+            .map(|flows| flows.iter().map(text).collect());
+        let variant = text(&node["index"]);
+        let key = utils::fingerprint(&dir.join(&variant)).unwrap_or_else(|| variant.clone());
+        // 内容相同的变体只比较第一个
+        nodes
+            .entry(key)
+            .or_insert((variant, text(&node["result"]), flows.unwrap_or_default()));
+        for child in node["variants"].as_array().into_iter().flatten() {
+            walk(dir, child, nodes);
+        }
+    }
+
+    let mut nodes = BTreeMap::new();
+    // 无法评估的 testcase 没有评估树
+    if let Ok(file) = std::fs::File::open(path) {
+        let root: Value = serde_json::from_reader(file).expect("Invalid evalTree.json");
+        walk(path.parent().unwrap_or(Path::new(".")), &root, &mut nodes);
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EvalResult, EvalResults};
+
+    #[test]
+    fn test_diff() {
+        let run = |name: &str, num: &str, child: EvalResults, tp: usize| {
+            let dir = PathBuf::from("./output/diff").join(name);
+            let testcase = dir.join("miri").join("sp-system-01");
+            // 变体编号不同, 内容相同
+            for (variant, code) in [("0000-0-0", "fn main() {}\n"), (num, "fn main() { f() }\n")] {
+                let variant = testcase.join(variant);
+                std::fs::create_dir_all(&variant).unwrap();
+                let code = format!("// Variant: {}\n{}", num, code);
+                std::fs::write(variant.join("POS.rs"), &code).unwrap();
+                std::fs::write(variant.join("NEG.rs"), &code).unwrap();
+            }
+            std::fs::write(
+                dir.join("miri/EvalSummary.csv"),
+                format!("编号,ID,变体,TP,FN\n000,sp-system-01,2,{},{}\n", tp, 2 - tp),
+            )
+            .unwrap();
+            let tree = serde_json::json!({
+                "index": "0000-0-0",
+                "result": EvalResults(EvalResult::TP, EvalResult::TN),
+                "variants": [{ "index": num, "result": child, "flows": ["Array"] }],
+            });
+            std::fs::write(testcase.join("evalTree.json"), tree.to_string()).unwrap();
+            dir
+        };
+        let before = run(
+            "a",
+            "0001-1-1",
+            EvalResults(EvalResult::TP, EvalResult::TN),
+            2,
+        );
+        let after = run(
+            "b",
+            "0004-1-1",
+            EvalResults(EvalResult::FN, EvalResult::TN),
+            1,
+        );
+
+        let diff = Diff::new(&before, &after);
+        assert_eq!(diff.regressions(), 1);
+        assert_eq!(diff.variants[0].variant, "0004-1-1");
+        assert_eq!(diff.unmatched, (0, 0));
+        assert_eq!(
+            (
+                diff.variants[0].before.as_str(),
+                diff.variants[0].after.as_str()
+            ),
+            ("TP/TN", "FN/TN")
+        );
+        assert_eq!(diff.testcases[0].counts.len(), 2);
+        assert!(diff
+            .render(Format::Text)
+            .contains("Regression: miri sp-system-01 0004-1-1 [Array]: TP/TN -> FN/TN"));
+        assert!(diff
+            .render(Format::Markdown)
+            .contains("| Regression | miri |"));
+        let json: Value = serde_json::from_str(&diff.render(Format::Json)).unwrap();
+        assert_eq!(json["variants"][0]["kind"], "regression");
+
+        let reverse = Diff::new(&after, &before);
+        assert_eq!((reverse.regressions(), reverse.fixes), (0, 1));
+        std::fs::remove_dir_all("./output/diff").unwrap();
+    }
+}
@@ -4,12 +4,45 @@ use serde::{ser::SerializeSeq as _, Serialize, Serializer};
 
 use crate::{EvalResult, EvalResults};
 
+/// Every kind of result of a variant, in the order of the legends
+pub(crate) const RESULTS: [EvalResults; 9] = [
+    EvalResults(EvalResult::TP, EvalResult::TN),
+    EvalResults(EvalResult::TP, EvalResult::FP),
+    EvalResults(EvalResult::FN, EvalResult::FP),
+    EvalResults(EvalResult::FN, EvalResult::TN),
+    EvalResults(EvalResult::Err, EvalResult::Err),
+    EvalResults(EvalResult::Timeout, EvalResult::Timeout),
+    EvalResults(EvalResult::OutOfMemory, EvalResult::OutOfMemory),
+    EvalResults(EvalResult::CompileError, EvalResult::CompileError),
+    EvalResults(EvalResult::InvalidTruth, EvalResult::InvalidTruth),
+];
+
+/// Colour of a node with the result `res`, in DOT and in CSS
+pub(crate) fn color(res: EvalResults) -> &'static str {
+    match (res.0, res.1) {
+        (EvalResult::CompileError, _) | (_, EvalResult::CompileError) => "white",
+        (EvalResult::InvalidTruth, _) | (_, EvalResult::InvalidTruth) => "lightgray",
+        (EvalResult::Timeout, _) | (_, EvalResult::Timeout) => "purple",
+        (EvalResult::OutOfMemory, _) | (_, EvalResult::OutOfMemory) => "brown",
+        (EvalResult::Err, _) | (_, EvalResult::Err) => "red",
+        (EvalResult::TP, EvalResult::TN) => "green",
+        (EvalResult::TP, EvalResult::FP) => "blue", // 误报
+        (EvalResult::FN, EvalResult::FP) => "gray", // 漏报 + 误报
+        (EvalResult::FN, EvalResult::TN) => "orange", // 漏报
+        _ => unreachable!(),
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub(crate) struct EvalNode {
     #[serde(rename = "index")]
     name: String,
     #[serde(rename = "result")]
     res: EvalResults,
+    /// Flows the source is nested in, from the innermost
+    #[serde(rename = "flows", skip_serializing_if = "Vec::is_empty")]
+    chain: Vec<String>,
+    outputs: Outputs,
     #[serde(rename = "variants", serialize_with = "EvalNode::serialize_children")]
     children: Vec<Rc<RefCell<EvalNode>>>,
 }
@@ -20,6 +53,8 @@ impl EvalNode {
         Rc::new(RefCell::new(Self {
             name: name.to_string(),
             res,
+            chain: Vec::new(),
+            outputs: Outputs::new(name, res),
             children: Vec::new(),
         }))
     }
@@ -29,15 +64,7 @@ impl EvalNode {
         let node_id = *counter; // 当前节点的唯一 ID
         *counter += 1;
 
-        // 定义节点的颜色
-        let color = match (self.res.0, self.res.1) {
-            (EvalResult::Err, _) | (_, EvalResult::Err) => "red",
-            (EvalResult::TP, EvalResult::TN) => "green",
-            (EvalResult::TP, EvalResult::FP) => "blue", // 误报
-            (EvalResult::FN, EvalResult::FP) => "gray", // 漏报 + 误报
-            (EvalResult::FN, EvalResult::TN) => "orange", // 漏报
-            _ => unreachable!(),
-        };
+        let color = color(self.res);
 
         // 添加当前节点
         dot.push_str(&format!(
@@ -71,6 +98,49 @@ impl EvalNode {
     }
 }
 
+/// Files of a variant, relative to the directory of `evalTree.json`
+#[derive(Debug, Serialize)]
+pub(crate) struct Outputs {
+    #[serde(rename = "POS")]
+    pos: Artifacts,
+    #[serde(rename = "NEG")]
+    neg: Artifacts,
+}
+
+/// Source of a program and, if the tool was run on it, the tool output
+#[derive(Debug, Serialize)]
+struct Artifacts {
+    code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr: Option<String>,
+    /// Exit status, wall time and findings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execution: Option<String>,
+}
+
+impl Outputs {
+    /// Files written by [`crate::utils::write`] and [`crate::utils::write_execution`] for the
+    /// variant `name`; the tool is not run on invalid variants
+    fn new(name: &str, res: EvalResults) -> Self {
+        let artifacts = |program: &str| {
+            let file = |ext: &str| format!("{}/{}.{}", name, program, ext);
+            let executed = !res.is_invalid();
+            Artifacts {
+                code: file("rs"),
+                stdout: executed.then(|| file("stdout")),
+                stderr: executed.then(|| file("stderr")),
+                execution: executed.then(|| file("json")),
+            }
+        };
+        Outputs {
+            pos: artifacts("POS"),
+            neg: artifacts("NEG"),
+        }
+    }
+}
+
 pub(crate) struct EvalTree {
     root: Option<Rc<RefCell<EvalNode>>>,
     node_map: HashMap<String, Rc<RefCell<EvalNode>>>,
@@ -85,11 +155,6 @@ impl EvalTree {
         }
     }
 
-    /// 获取节点数
-    pub(crate) fn count_nodes(&self) -> usize {
-        self.node_map.len()
-    }
-
     /// 设置根节点
     pub(crate) fn set_root(&mut self, root: Rc<RefCell<EvalNode>>) {
         self.node_map
@@ -102,15 +167,17 @@ impl EvalTree {
         self.node_map.get(name).cloned()
     }
 
-    /// 添加子节点
+    /// 添加子节点, 并记录其嵌套的 flow
     pub(crate) fn add_child(
         &mut self,
         parent_name: &str,
         child_name: &str,
         child_res: EvalResults,
+        chain: &[String],
     ) -> Result<(), String> {
         if let Some(parent) = self.get_node(parent_name) {
             let child = EvalNode::new(child_name, child_res);
+            child.borrow_mut().chain = chain.to_vec();
             parent.borrow_mut().children.push(Rc::clone(&child));
             self.node_map
                 .insert(child_name.to_string(), Rc::clone(&child));
@@ -160,47 +227,81 @@ mod test {
             "Root",
             "Child1",
             EvalResults(EvalResult::TP, EvalResult::TN),
+            &[String::from("Function call")],
         )
         .unwrap();
         tree.add_child(
             "Root",
             "Child2",
             EvalResults(EvalResult::TP, EvalResult::FP),
+            &[],
         )
         .unwrap();
         tree.add_child(
             "Child1",
             "GrandChild1",
             EvalResults(EvalResult::FN, EvalResult::TN),
+            &[],
         )
         .unwrap();
         tree.add_child(
             "Child2",
             "GrandChild2",
             EvalResults(EvalResult::FN, EvalResult::FP),
+            &[],
+        )
+        .unwrap();
+        tree.add_child(
+            "Child1",
+            "GrandChild3",
+            EvalResults(EvalResult::Timeout, EvalResult::TN),
+            &[],
         )
         .unwrap();
+        tree.add_child(
+            "Child1",
+            "GrandChild4",
+            EvalResults(EvalResult::TP, EvalResult::OutOfMemory),
+            &[],
+        )
+        .unwrap();
+
+        // 仅嵌套的节点记录 flow
+        let json = serde_json::to_value(&*root.borrow()).unwrap();
+        assert!(json.get("flows").is_none());
+        assert_eq!(json["variants"][0]["flows"][0], "Function call");
+        assert_eq!(json["outputs"]["NEG"]["stderr"], "Root/NEG.stderr");
+        let invalid = EvalNode::new(
+            "Invalid",
+            EvalResults(EvalResult::CompileError, EvalResult::CompileError),
+        );
+        let json = serde_json::to_value(&*invalid.borrow()).unwrap();
+        assert_eq!(json["outputs"]["POS"]["code"], "Invalid/POS.rs");
+        assert!(json["outputs"]["POS"].get("stdout").is_none());
 
         // 生成 DOT 文件内容
         let dot_content = tree.to_dot();
         println!("DOT Representation:\n{}", dot_content);
+        assert!(dot_content.contains("fillcolor=purple"));
+        assert!(dot_content.contains("fillcolor=brown"));
 
         // 保存到文件
         let dot_path = "tree.dot";
         std::fs::write(dot_path, dot_content).expect("Failed to write DOT file");
 
         // 调用 Graphviz 将 DOT 转为图片
-        let output = std::process::Command::new("dot")
-            .args(&["-Tpng", "-o", "tree.png", dot_path])
+        match std::process::Command::new("dot")
+            .args(["-Tpng", "-o", "tree.png", dot_path])
             .output()
-            .expect("Failed to execute Graphviz");
-        if output.status.success() {
-            println!("EvalTree image generated: tree.png");
-        } else {
-            eprintln!(
+        {
+            Ok(output) if output.status.success() => {
+                println!("EvalTree image generated: tree.png")
+            }
+            Ok(output) => eprintln!(
                 "Error generating image: {}",
                 String::from_utf8_lossy(&output.stderr)
-            );
+            ),
+            Err(e) => eprintln!("Failed to execute Graphviz: {}", e),
         }
     }
 }
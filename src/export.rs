@@ -0,0 +1,298 @@
+use std::{fmt::Write as _, path::Path};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{adapter::Finding, utils::escape, EvalResult, EvalRow};
+
+/// Execution of a tool on a program, as written by [`crate::utils::write_execution`]
+#[derive(Debug, Default, Deserialize)]
+struct Record {
+    #[serde(default)]
+    time: f64,
+    #[serde(default)]
+    findings: Vec<Finding>,
+    #[serde(default)]
+    sinks: Vec<usize>,
+}
+
+impl Record {
+    /// Record of the `name` program of `variant` in the tool directory `dir`; `None` if the tool
+    /// was not run on it, e.g. on a variant which does not compile
+    fn read(dir: &Path, row: &EvalRow, name: &str) -> Option<Self> {
+        let path = dir
+            .join(&row.id)
+            .join(&row.variant)
+            .join(format!("{}.json", name));
+        let file = std::fs::File::open(path).ok()?;
+        serde_json::from_reader(file).ok()
+    }
+}
+
+/// JUnit test case of one program of a variant
+struct Case<'a> {
+    row: &'a EvalRow,
+    name: &'static str,
+    time: f64,
+    res: EvalResult,
+}
+
+impl Case<'_> {
+    /// Element, type and message of the outcome; `None` if the verdict is correct
+    fn outcome(&self) -> Option<(&'static str, &'static str, String)> {
+        let file = format!("{}/{}/{}.rs", self.row.id, self.row.variant, self.name);
+        match self.res {
+            EvalResult::TP | EvalResult::TN => None,
+            EvalResult::FN => Some(("failure", "FN", format!("Bug of {} not detected", file))),
+            EvalResult::FP => Some(("failure", "FP", format!("Bug reported on {}", file))),
+            EvalResult::Err => Some(("error", "Err", format!("Tool failed on {}", file))),
+            EvalResult::Timeout => Some(("error", "Timeout", format!("Timeout on {}", file))),
+            EvalResult::OutOfMemory => {
+                Some(("error", "OutOfMemory", format!("Out of memory on {}", file)))
+            }
+            EvalResult::CompileError => Some((
+                "skipped",
+                "CompileError",
+                String::from("Variant does not compile"),
+            )),
+            EvalResult::InvalidTruth => Some((
+                "skipped",
+                "InvalidTruth",
+                String::from("Variant has an invalid ground truth"),
+            )),
+        }
+    }
+}
+
+/// Counts of the test cases with each outcome, as the attributes of a test suite
+fn attributes<'a, 'b: 'a>(cases: impl Iterator<Item = &'a Case<'b>>) -> String {
+    let (mut tests, mut failures, mut errors, mut skipped, mut time) = (0, 0, 0, 0, 0.0);
+    for case in cases {
+        tests += 1;
+        time += case.time;
+        match case.outcome() {
+            Some(("failure", _, _)) => failures += 1,
+            Some(("error", _, _)) => errors += 1,
+            Some(_) => skipped += 1,
+            None => {}
+        }
+    }
+    format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+        tests, failures, errors, skipped, time
+    )
+}
+
+/// JUnit XML of the `i`-th tool with one test suite per testcase, and one test case per POS or
+/// NEG program of each variant
+pub(crate) fn junit(tool: &str, i: usize, rows: &[&EvalRow], dir: &Path) -> String {
+    let mut suites = Vec::new();
+    for testcase in rows.chunk_by(|a, b| a.id == b.id) {
+        let mut cases = Vec::new();
+        for row in testcase {
+            let res = row.results[i];
+            for (name, res) in [("POS", res.0), ("NEG", res.1)] {
+                let time = Record::read(dir, row, name).map_or(0.0, |record| record.time);
+                cases.push(Case {
+                    row,
+                    name,
+                    time,
+                    res,
+                });
+            }
+        }
+        suites.push((testcase[0].id.as_str(), cases));
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"{}\" {}>",
+        escape(tool),
+        attributes(suites.iter().flat_map(|(_, cases)| cases))
+    );
+    for (id, cases) in suites.iter() {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" {}>",
+            escape(id),
+            attributes(cases.iter())
+        );
+        for case in cases {
+            let _ = write!(
+                xml,
+                "    <testcase classname=\"{}.{}\" name=\"{} {}\" time=\"{:.3}\"",
+                escape(tool),
+                escape(id),
+                escape(&case.row.variant),
+                case.name,
+                case.time
+            );
+            match case.outcome() {
+                None => xml.push_str("/>\n"),
+                Some((element, kind, message)) => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n      <{} type=\"{}\" message=\"{}\"/>\n    </testcase>",
+                        element,
+                        kind,
+                        escape(&message)
+                    );
+                }
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// SARIF log of the `i`-th tool: the bugs expected at the SINK lines of the POS programs, and the
+/// findings reported on the POS and NEG programs, located in the generated `POS.rs`/`NEG.rs`
+///
+/// Results of a program with a wrong verdict (FN or FP) are errors, the others are notes.
+pub(crate) fn sarif(tool: &str, i: usize, rows: &[&EvalRow], dir: &Path) -> Value {
+    let mut results = Vec::new();
+    for row in rows {
+        let res = row.results[i];
+        for (name, res) in [("POS", res.0), ("NEG", res.1)] {
+            let Some(record) = Record::read(dir, row, name) else {
+                continue;
+            };
+            let uri = format!("{}/{}/{}.rs", row.id, row.variant, name);
+            let level = match res {
+                EvalResult::FN | EvalResult::FP => "error",
+                _ => "note",
+            };
+            let properties = json!({
+                "testcase": row.id,
+                "variant": row.variant,
+                "program": name,
+                "verdict": format!("{:?}", res),
+            });
+            // NEG 程序中 GOOD SINK 处不应有缺陷
+            if name == "POS" {
+                for &line in record.sinks.iter() {
+                    results.push(json!({
+                        "ruleId": "expected",
+                        "level": level,
+                        "message": { "text": format!("Bug of {} expected at the SINK", row.id) },
+                        "locations": [location(&uri, Some(line))],
+                        "properties": properties,
+                    }));
+                }
+            }
+            for finding in record.findings.iter() {
+                let mut text = match &finding.kind {
+                    Some(kind) => format!("{}: {}", kind, finding.message),
+                    None => finding.message.clone(),
+                };
+                // 仅 main.rs 中的位置对应生成的程序
+                let in_main = finding.in_main();
+                let line = finding.line.filter(|_| in_main);
+                if let (false, Some(file)) = (in_main, &finding.file) {
+                    let _ = write!(text, " (at {}:{})", file, finding.line.unwrap_or(0));
+                }
+                results.push(json!({
+                    "ruleId": "reported",
+                    "level": level,
+                    "message": { "text": text },
+                    "locations": [location(&uri, line)],
+                    "properties": properties,
+                }));
+            }
+        }
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": tool,
+                    "rules": [
+                        {
+                            "id": "expected",
+                            "shortDescription": { "text": "Bug expected at a SINK line of a POS program" },
+                        },
+                        {
+                            "id": "reported",
+                            "shortDescription": { "text": "Finding reported by the tool" },
+                        },
+                    ],
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// Physical location in `uri`, relative to the tool directory, at `line` if known
+fn location(uri: &str, line: Option<usize>) -> Value {
+    let mut location = json!({ "artifactLocation": { "uri": uri } });
+    if let Some(line) = line {
+        location["region"] = json!({ "startLine": line });
+    }
+    json!({ "physicalLocation": location })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EvalResults;
+
+    #[test]
+    fn test_export() {
+        let dir = Path::new("./output/export");
+        let variant = dir.join("sp-system-01").join("0001-1-1");
+        std::fs::create_dir_all(&variant).unwrap();
+        let finding = r#"{"kind": "UB", "file": "src/main.rs", "line": 3, "message": "a<b"}"#;
+        std::fs::write(
+            variant.join("POS.json"),
+            r#"{"time": 0.5, "findings": [], "sinks": [4]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            variant.join("NEG.json"),
+            format!(
+                r#"{{"time": 0.25, "findings": [{}], "sinks": [4]}}"#,
+                finding
+            ),
+        )
+        .unwrap();
+        let row = |variant, pos, neg| EvalRow::test(variant, None, &[], EvalResults(pos, neg));
+        let rows = [
+            row(
+                "0000-0-0",
+                EvalResult::CompileError,
+                EvalResult::CompileError,
+            ),
+            row("0001-1-1", EvalResult::FN, EvalResult::FP),
+        ];
+        let rows: Vec<_> = rows.iter().collect();
+
+        let xml = junit("miri", 0, &rows, dir);
+        assert!(xml.contains(
+            "<testsuites name=\"miri\" tests=\"4\" failures=\"2\" errors=\"0\" skipped=\"2\" time=\"0.750\">"
+        ));
+        assert!(xml.contains(
+            "<testcase classname=\"miri.sp-system-01\" name=\"0001-1-1 POS\" time=\"0.500\">\n      \
+             <failure type=\"FN\" message=\"Bug of sp-system-01/0001-1-1/POS.rs not detected\"/>"
+        ));
+
+        let sarif = sarif("miri", 0, &rows, dir);
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        let (expected, reported) = (&results[0], &results[1]);
+        assert_eq!(expected["ruleId"], "expected");
+        assert_eq!(expected["properties"]["verdict"], "FN");
+        let location = &reported["locations"][0]["physicalLocation"];
+        assert_eq!(
+            location["artifactLocation"]["uri"],
+            "sp-system-01/0001-1-1/NEG.rs"
+        );
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(reported["message"]["text"], "UB: a<b");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
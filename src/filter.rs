@@ -0,0 +1,148 @@
+use log::error;
+use regex::Regex;
+
+use crate::config::Testcase;
+
+/// Selection of the testcases to evaluate
+///
+/// Every pattern is a glob (`*` and `?`), or a regex when written between slashes (`/.../`).
+/// A testcase is selected when it matches every non-empty criterion, and any pattern of each.
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    /// Positional indices in `testcases.yaml`
+    pub indices: Vec<usize>,
+    /// Patterns of the testcase IDs
+    pub ids: Vec<String>,
+    /// Patterns of the tags, e.g. `SP(Initialized)`
+    pub tags: Vec<String>,
+    /// Patterns of the features, e.g. `Function std::alloc::*`
+    pub features: Vec<String>,
+    /// Patterns of the UB categories, i.e. the tags `UB(...)`
+    pub ubs: Vec<String>,
+}
+
+impl Selection {
+    /// Compile the patterns, exiting on an invalid one
+    pub(crate) fn filter(&self) -> Filter {
+        let compile = |patterns: &[String]| -> Vec<Pattern> {
+            patterns
+                .iter()
+                .map(|pattern| match Pattern::new(pattern) {
+                    Ok(pattern) => pattern,
+                    Err(err) => {
+                        error!("Error: Invalid pattern {}: {}", pattern, err);
+                        std::process::exit(1);
+                    }
+                })
+                .collect()
+        };
+        Filter {
+            indices: self.indices.clone(),
+            ids: compile(&self.ids),
+            tags: compile(&self.tags),
+            features: compile(&self.features),
+            ubs: compile(&self.ubs),
+        }
+    }
+}
+
+pub(crate) struct Filter {
+    indices: Vec<usize>,
+    ids: Vec<Pattern>,
+    tags: Vec<Pattern>,
+    features: Vec<Pattern>,
+    ubs: Vec<Pattern>,
+}
+
+impl Filter {
+    /// Check if the testcase `idx` is selected
+    pub(crate) fn matches(&self, idx: usize, testcase: &Testcase) -> bool {
+        let ubs = testcase
+            .tags()
+            .iter()
+            .filter_map(|tag| tag.strip_prefix("UB(")?.strip_suffix(')'));
+        (self.indices.is_empty() || self.indices.contains(&idx))
+            && any(&self.ids, std::iter::once(testcase.id()))
+            && any(&self.tags, testcase.tags().iter().map(String::as_str))
+            && any(
+                &self.features,
+                testcase.features().iter().map(String::as_str),
+            )
+            && any(&self.ubs, ubs)
+    }
+}
+
+/// Check if any of `values` matches any of `patterns` (always true without patterns)
+fn any<'a>(patterns: &[Pattern], mut values: impl Iterator<Item = &'a str>) -> bool {
+    patterns.is_empty() || values.any(|value| patterns.iter().any(|p| p.is_match(value)))
+}
+
+/// A glob pattern matching the whole text, or a regex `/.../` matching any part of it
+pub(crate) struct Pattern(Regex);
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Result<Self, regex::Error> {
+        if let Some(re) = pattern.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+            return Regex::new(re).map(Pattern);
+        }
+        let glob: String = pattern
+            .chars()
+            .map(|c| match c {
+                '*' => String::from(".*"),
+                '?' => String::from("."),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect();
+        Regex::new(&format!("^{}$", glob)).map(Pattern)
+    }
+
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Testcases;
+
+    #[test]
+    fn test_pattern() {
+        let pattern = Pattern::new("UB(Dangling*)").unwrap();
+        assert!(pattern.is_match("UB(Dangling pointer access)"));
+        assert!(!pattern.is_match("SP(UB(Dangling pointer access))"));
+
+        let pattern = Pattern::new("/^sp-(rc|box)-0[12]$/").unwrap();
+        assert!(pattern.is_match("sp-rc-02"));
+        assert!(!pattern.is_match("sp-rc-03"));
+        assert!(Pattern::new("/(/").is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let testcases = Testcases::from_file("config/testcases.yaml");
+        let select = |selection: Selection| -> Vec<usize> {
+            let filter = selection.filter();
+            (0..testcases.len())
+                .filter(|&idx| filter.matches(idx, &testcases[idx]))
+                .collect()
+        };
+
+        assert_eq!(select(Selection::default()).len(), testcases.len());
+        let rc = select(Selection {
+            ids: vec![String::from("sp-rc-*")],
+            ..Default::default()
+        });
+        assert_eq!(rc.len(), 4);
+
+        // 条件之间为交集
+        let uaf = select(Selection {
+            ids: vec![String::from("sp-rc-*")],
+            ubs: vec![String::from("Dangling pointer access")],
+            features: vec![String::from("/Rc/")],
+            ..Default::default()
+        });
+        assert!(!uaf.is_empty() && uaf.len() < rc.len());
+        assert!(uaf.iter().all(|idx| rc.contains(idx)));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use rand::Rng;
+
+use crate::{
+    config::{Flows, Testcase},
+    Expr, Exprs,
+};
+
+/// A generated variant and the expression it is nested in
+pub(crate) struct Variant {
+    pub(crate) parent: String,
+    pub(crate) expr: Expr,
+}
+
+/// Generation of the variants of a testcase, independent of any tool verdict
+pub(crate) struct Generator<'a> {
+    flows: &'a Flows,
+    testcase: &'a Testcase,
+    length: usize,
+    depth: usize,
+}
+
+impl<'a> Generator<'a> {
+    pub(crate) fn new(
+        flows: &'a Flows,
+        testcase: &'a Testcase,
+        length: usize,
+        depth: usize,
+    ) -> Self {
+        Generator {
+            flows,
+            testcase,
+            length,
+            depth,
+        }
+    }
+
+    /// Check if `expr` can be nested further and used as an `EXPRE!()` filling
+    fn accepts(&self, expr: &Expr) -> bool {
+        expr.length < self.length && expr.depth < self.depth
+    }
+
+    /// Visit the variants of a BFS over the flows, filling each `EXPRE!()` with a random
+    /// expression among the accepted ones
+    pub(crate) fn random<R, F>(&self, rng: &mut R, mut visit: F)
+    where
+        R: Rng,
+        F: FnMut(Variant),
+    {
+        let mut num = 1; // 0 为原始 testcase
+                         // exprs 初始化
+        let mut exprs = Exprs::new();
+        exprs.push(Expr::source());
+
+        // sources 队列
+        let mut sources = VecDeque::new();
+        sources.push_back(Expr::source());
+        while let Some(src) = sources.pop_front() {
+            for flow in self.flows.iter() {
+                let expr = flow.into_expr(num, &src, &exprs, self.testcase, rng);
+                num += 1;
+                if self.accepts(&expr) {
+                    sources.push_back(expr.clone());
+                    exprs.push(expr.clone());
+                }
+                visit(Variant {
+                    parent: src.num.clone(),
+                    expr,
+                });
+            }
+        }
+    }
+
+    /// Visit every variant, level by level
+    ///
+    /// At level `n`, every accepted expression of level `n - 1` is nested into every flow, and
+    /// the `EXPRE!()` slots are filled with every combination of the expressions accepted in
+    /// the previous levels.
+    pub(crate) fn exhaustive<F>(&self, mut visit: F)
+    where
+        F: FnMut(Variant),
+    {
+        let mut num = 1; // 0 为原始 testcase
+        let mut exprs = vec![Expr::source()];
+        let mut sources = vec![Expr::source()];
+        while !sources.is_empty() {
+            let mut accepted = Vec::new();
+            for src in sources.iter() {
+                for flow in self.flows.iter() {
+                    for expr in flow.into_exprs(num, src, &exprs, self.testcase) {
+                        num += 1;
+                        if self.accepts(&expr) {
+                            accepted.push(expr.clone());
+                        }
+                        visit(Variant {
+                            parent: src.num.clone(),
+                            expr,
+                        });
+                    }
+                }
+            }
+            exprs.extend(accepted.iter().cloned());
+            sources = accepted;
+        }
+    }
+
+    /// Number of variants visited by [`Generator::exhaustive`], computed without generating them
+    pub(crate) fn count(&self) -> u128 {
+        // 按 depth 统计的 expr 数量
+        let mut exprs: BTreeMap<usize, u128> = BTreeMap::from([(0, 1)]);
+        let mut sources: BTreeMap<usize, u128> = BTreeMap::from([(0, 1)]);
+        let mut total: u128 = 0;
+        let mut length = 0;
+        while !sources.is_empty() {
+            length += 1;
+            let mut accepted: BTreeMap<usize, u128> = BTreeMap::new();
+            for (&src_depth, &src_count) in sources.iter() {
+                for flow in self.flows.iter() {
+                    let slots = flow.slots() as u32;
+                    // 填充的最大 depth 为 m 的组合数: S(<=m)^k - S(<m)^k
+                    let mut children: BTreeMap<usize, u128> = BTreeMap::new();
+                    if slots == 0 {
+                        children.insert(src_depth, 1);
+                    } else {
+                        let mut below: u128 = 0;
+                        for (&depth, &count) in exprs.iter() {
+                            let upto = below.saturating_add(count);
+                            let combinations =
+                                upto.saturating_pow(slots) - below.saturating_pow(slots);
+                            *children.entry(src_depth.max(depth + 1)).or_default() += combinations;
+                            below = upto;
+                        }
+                    }
+                    for (depth, count) in children {
+                        let count = count.saturating_mul(src_count);
+                        total = total.saturating_add(count);
+                        if length < self.length && depth < self.depth {
+                            *accepted.entry(depth).or_default() += count;
+                        }
+                    }
+                }
+            }
+            for (&depth, &count) in accepted.iter() {
+                *exprs.entry(depth).or_default() += count;
+            }
+            sources = accepted;
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Testcases;
+
+    #[test]
+    fn test_exhaustive() {
+        let testcases = Testcases::from_file("config/testcases.yaml");
+        let flows = Flows::from_file("config/expressions.yaml");
+
+        // length 1: 每个 flow 的每个 EXPRE!() 只能填充 SOURCE!()
+        let generator = Generator::new(&flows, &testcases[0], 1, 3);
+        let mut count = 0;
+        generator.exhaustive(|_| count += 1);
+        assert_eq!(count, flows.len());
+        assert_eq!(generator.count(), count as u128);
+
+        // depth 1: 仅没有 EXPRE!() 的 flow 被接受, 作为第二层的填充
+        let generator = Generator::new(&flows, &testcases[0], 2, 1);
+        let mut nums = std::collections::HashSet::new();
+        generator.exhaustive(|variant| {
+            assert!(variant.expr.length <= 2);
+            nums.insert(variant.expr.num);
+        });
+        assert_eq!(generator.count(), nums.len() as u128);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::Program;
+
+/// Cargo settings of a testcase, rendered into the manifest of the harness
+///
+/// ```yaml
+/// manifest:
+///   edition: 2021
+///   dependencies:
+///     libc: "0.2"
+///     vendored: { path: vendor/vendored }
+///   features: [libc/extra_traits]
+///   profile:
+///     dev: { overflow-checks: false }
+/// ```
+///
+/// Relative dependency paths are relative to the directory of `testcases.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    #[serde(default = "Manifest::default_edition")]
+    edition: u16,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+    /// Features enabled by default, e.g. `libc/extra_traits`
+    #[serde(default)]
+    features: Vec<String>,
+    /// Overrides of `[profile.<name>]`
+    #[serde(default)]
+    profile: BTreeMap<String, toml::Table>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            edition: Manifest::default_edition(),
+            dependencies: BTreeMap::new(),
+            features: Vec::new(),
+            profile: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    fn default_edition() -> u16 {
+        2018
+    }
+
+    /// Make the relative dependency paths absolute, relative to `base`
+    pub(crate) fn resolve(&mut self, base: &Path) {
+        for dependency in self.dependencies.values_mut() {
+            let Some(toml::Value::String(path)) =
+                dependency.as_table_mut().and_then(|t| t.get_mut("path"))
+            else {
+                continue;
+            };
+            if Path::new(path.as_str()).is_relative() {
+                let absolute = std::path::absolute(base.join(&path)).expect("Invalid path");
+                *path = absolute.to_string_lossy().to_string();
+            }
+        }
+    }
+
+    /// `Cargo.toml` of the harness; the empty `[workspace]` keeps it out of any enclosing
+    /// workspace
+    pub(crate) fn render(&self) -> String {
+        let mut package = toml::Table::new();
+        package.insert("name".into(), "harness".into());
+        package.insert("version".into(), "0.1.0".into());
+        package.insert("edition".into(), self.edition.to_string().into());
+
+        let mut manifest = toml::Table::new();
+        manifest.insert("package".into(), package.into());
+        let dependencies = self.dependencies.clone().into_iter().collect();
+        manifest.insert("dependencies".into(), toml::Value::Table(dependencies));
+        if !self.features.is_empty() {
+            let mut features = toml::Table::new();
+            features.insert("default".into(), self.features.clone().into());
+            manifest.insert("features".into(), features.into());
+        }
+        if !self.profile.is_empty() {
+            let profile = self
+                .profile
+                .iter()
+                .map(|(name, table)| (name.clone(), table.clone().into()))
+                .collect();
+            manifest.insert("profile".into(), toml::Value::Table(profile));
+        }
+        manifest.insert("workspace".into(), toml::Table::new().into());
+        toml::to_string(&manifest).expect("Manifest serialization failed")
+    }
+}
+
+/// Where a tool builds the harness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Target {
+    /// `CARGO_TARGET_DIR` shared by the compile check and the tools of a worker
+    #[default]
+    Shared,
+    /// `CARGO_TARGET_DIR` of the tool alone, e.g. when its flags invalidate the shared build
+    Isolated,
+    /// `CARGO_TARGET_DIR` left unset, for tools which do not honour it
+    Harness,
+}
+
+/// Pool of harness crates, one per worker thread, rewritten for every program
+#[derive(Debug, Clone)]
+pub(crate) struct Harnesses {
+    root: PathBuf,
+}
+
+/// Harness crate of a worker
+pub(crate) struct Harness {
+    pub(crate) dir: PathBuf,
+    root: PathBuf,
+    worker: String,
+}
+
+impl Harnesses {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Harnesses { root }
+    }
+
+    /// Harness of the current thread, created from the template on first use
+    ///
+    /// A testcase is evaluated on a single rayon thread, so its programs never share a harness
+    /// with the ones of another testcase.
+    pub(crate) fn worker(&self) -> Harness {
+        let worker = match rayon::current_thread_index() {
+            Some(index) => format!("worker-{}", index),
+            // 非 rayon 线程 (如测试) 以线程 ID 区分
+            None => {
+                let id = format!("{:?}", std::thread::current().id());
+                format!("thread-{}", id.trim_matches(|c: char| !c.is_ascii_digit()))
+            }
+        };
+        let dir = self.root.join(&worker);
+        if !dir.join("Cargo.toml").exists() {
+            Harnesses::create(&dir).expect("Harness creation failed");
+            info!("Generate Harness: {}", dir.display());
+        }
+        Harness {
+            dir,
+            root: self.root.clone(),
+            worker,
+        }
+    }
+
+    fn create(dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}\n")?;
+        std::fs::write(dir.join("Cargo.toml"), Manifest::default().render())
+    }
+
+    /// Check if `program` compiles in the harness of the current thread, with `cargo check`
+    pub(crate) fn check(&self, program: &Program) -> bool {
+        let harness = self.worker();
+        program.into_harness(&harness.dir);
+        let mut command = Command::new("cargo");
+        command
+            .args(["check", "--quiet", "--manifest-path"])
+            .arg(harness.dir.join("Cargo.toml"));
+        if let Some(target_dir) = harness.target_dir(Target::Shared, "check") {
+            command.env("CARGO_TARGET_DIR", target_dir);
+        }
+        command
+            .output()
+            .expect("cargo command failed to start")
+            .status
+            .success()
+    }
+
+    /// Remove the harnesses and their build artifacts
+    pub(crate) fn cleanup(&self) {
+        match std::fs::remove_dir_all(&self.root) {
+            Ok(()) => info!("Remove Harnesses: {}", self.root.display()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => panic!("Failed to remove {}: {}", self.root.display(), err),
+        }
+    }
+}
+
+impl Harness {
+    /// `CARGO_TARGET_DIR` of `tool` building this harness
+    pub(crate) fn target_dir(&self, target: Target, tool: &str) -> Option<PathBuf> {
+        let dir = self.root.join("target");
+        match target {
+            Target::Shared => Some(dir.join(&self.worker)),
+            Target::Isolated => Some(dir.join(tool).join(&self.worker)),
+            Target::Harness => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let mut manifest: Manifest = serde_yaml::from_str(
+            r#"
+edition: 2021
+dependencies:
+  libc: "0.2"
+  vendored: { path: vendor/vendored, default-features: false }
+features: [libc/extra_traits]
+profile:
+  dev: { overflow-checks: false }
+"#,
+        )
+        .unwrap();
+        manifest.resolve(Path::new("config"));
+        let rendered: toml::Table = toml::from_str(&manifest.render()).unwrap();
+
+        assert_eq!(rendered["package"]["edition"].as_str(), Some("2021"));
+        assert_eq!(rendered["dependencies"]["libc"].as_str(), Some("0.2"));
+        let path = rendered["dependencies"]["vendored"]["path"]
+            .as_str()
+            .unwrap();
+        assert!(Path::new(path).is_absolute() && path.ends_with("config/vendor/vendored"));
+        assert_eq!(
+            rendered["features"]["default"][0].as_str(),
+            Some("libc/extra_traits")
+        );
+        assert_eq!(
+            rendered["profile"]["dev"]["overflow-checks"].as_bool(),
+            Some(false)
+        );
+
+        let default: toml::Table = toml::from_str(&Manifest::default().render()).unwrap();
+        assert_eq!(default["package"]["edition"].as_str(), Some("2018"));
+        assert!(default.contains_key("workspace"));
+    }
+
+    #[test]
+    fn test_worker() {
+        let harnesses = Harnesses::new("./output/harnesses".into());
+        let harness = harnesses.worker();
+        assert!(harness.dir.join("Cargo.toml").exists());
+        // 同一线程复用同一 harness
+        assert_eq!(harnesses.worker().dir, harness.dir);
+        let other = std::thread::spawn(move || harnesses.worker().dir)
+            .join()
+            .unwrap();
+        assert_ne!(other, harness.dir);
+
+        let shared = harness.target_dir(Target::Shared, "miri").unwrap();
+        let isolated = harness.target_dir(Target::Isolated, "miri").unwrap();
+        assert_ne!(shared, isolated);
+        assert!(isolated.starts_with("./output/harnesses/target/miri"));
+        assert_eq!(harness.target_dir(Target::Harness, "miri"), None);
+
+        Harnesses::new("./output/harnesses".into()).cleanup();
+        assert!(!harness.dir.exists());
+    }
+}
@@ -1,229 +1,985 @@
+mod adapter;
+mod baseline;
 mod config;
+mod corpus;
+mod curve;
+mod diff;
 mod eval_tree;
+mod export;
+mod filter;
+mod generator;
+mod harness;
+mod minimizer;
+mod oracle;
+mod report;
 mod utils;
+mod validator;
 
-use config::{Flows, Testcases};
+pub use adapter::Limits;
+use adapter::{Adapter, Adapters, Execution};
+use baseline::Baseline;
+use config::{Flows, Kind, Recipe, Testcase, Testcases};
 use core::fmt;
+use corpus::Corpus;
+use curve::CurveReport;
+pub use diff::Format;
 use eval_tree::{EvalNode, EvalTree};
-use log::{error, info};
-use rand::Rng as _;
+pub use filter::Selection;
+use generator::{Generator, Variant};
+use harness::{Harnesses, Manifest};
+use log::{error, info, warn};
+use minimizer::Failure;
+use oracle::{Behaviour, GroundTruth, Oracle};
+use rand::{rngs::StdRng, Rng, SeedableRng as _};
 use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
 use serde::{Serialize, Serializer};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, HashMap, HashSet},
     ops::{Deref, DerefMut},
-    path::PathBuf,
-    process::{Command, Output},
+    path::{Path, PathBuf},
+    sync::Once,
 };
 use tabled::{Table, Tabled};
 
+/// Options of an evaluation run
+#[derive(Default)]
+pub struct Options {
+    /// Expression sequence length
+    pub length: usize,
+    /// Expression nesting depth
+    pub depth: usize,
+    /// Seed of the expression generation
+    pub seed: u64,
+    /// Enumerate every combination of expressions for the `EXPRE!()` slots
+    pub exhaustive: bool,
+    /// Evaluate the variants of a corpus instead of generating them
+    pub corpus: Option<PathBuf>,
+    /// Resource limits of each tool invocation
+    pub limits: Limits,
+    /// Minimize the variants on which a tool stops detecting the bug of their parent
+    pub minimize: bool,
+    /// Keep the harnesses and their build artifacts after the run
+    pub keep_harness: bool,
+    /// Reference oracles checking the POS/NEG labels of each variant, as `[<kind>=]<tool>`,
+    /// e.g. `miri` or `panic=run`
+    pub oracle: Vec<String>,
+    /// Known misses and false alarms of the tools, compared with the run
+    pub baseline: Option<PathBuf>,
+    /// Overwrite the baseline with the misses and false alarms of the run
+    pub update_baseline: bool,
+    /// Write the verdicts of each tool as JUnit XML, next to its `EvalSummary.csv`
+    pub junit: bool,
+    /// Write the expected and reported findings of each tool as a SARIF log
+    pub sarif: bool,
+}
+
+/// Generate a frozen variant corpus of the selected testcases [default: ALL] into `output`
+pub fn generate(config: PathBuf, selection: Selection, output: PathBuf, options: Options) {
+    let config = Config::new(
+        config,
+        options.length,
+        options.depth,
+        options.seed,
+        options.exhaustive,
+    );
+    let targets = config.targets(&selection);
+    std::fs::create_dir_all(&output).expect(" std::fs::create_dir_all failed");
+    let corpus = Corpus::generate(&config, &targets, &output);
+    let count: usize = corpus.testcases.iter().map(|t| t.variants.len()).sum();
+    println!(
+        "Generated {} variants of {} testcases into {}",
+        count,
+        targets.len(),
+        output.display()
+    );
+}
+
+/// Check the configuration `files` and print their diagnostics; returns false on any error
+pub fn validate(files: &[PathBuf]) -> bool {
+    let mut errors = 0;
+    let mut warnings = 0;
+    for file in files {
+        for diagnostic in validator::validate(file) {
+            match diagnostic.severity {
+                validator::Severity::Error => errors += 1,
+                validator::Severity::Warning => warnings += 1,
+            }
+            println!("{}", diagnostic);
+        }
+    }
+    println!(
+        "{} errors, {} warnings in {} files",
+        errors,
+        warnings,
+        files.len()
+    );
+    errors == 0
+}
+
+/// Print the verdict changes between the output directories of two runs; `false` when the
+/// regressions exceed `threshold`
+pub fn diff(before: &Path, after: &Path, format: Format, threshold: usize) -> bool {
+    let diff = diff::Diff::new(before, after);
+    print!("{}", diff.render(format));
+    diff.regressions() <= threshold
+}
+
 pub struct Evaluator {
-    executor: Executor,
-    config: Config,
-    targets: Vec<usize>,
+    executors: Vec<Executor>,
+    /// `None` when the variants come from a corpus
+    config: Option<Config>,
+    corpus: Option<(PathBuf, Corpus)>,
+    selection: Selection,
     output: PathBuf,
+    minimize: bool,
+    harnesses: Harnesses,
+    keep_harness: bool,
+    baseline: Option<PathBuf>,
+    update_baseline: bool,
+    junit: bool,
+    sarif: bool,
+    /// Oracles of the testcase kinds, at most one per kind
+    oracles: Vec<Oracle>,
 }
 
 impl Evaluator {
     pub fn new(
-        tool: PathBuf,
+        tools: Vec<String>,
         config: PathBuf,
-        targets: Vec<usize>,
-        length: usize,
-        depth: usize,
+        selection: Selection,
         output: PathBuf,
+        options: Options,
     ) -> Self {
-        utils::is_executable(&tool);
-        let harness = output.join("harness");
-        let output = output.join(tool.file_stem().unwrap());
+        let adapters = match config.join("tools.yaml") {
+            path if path.exists() => Adapters::from_file(path),
+            _ => Adapters::default(),
+        };
+        let harnesses = Harnesses::new(output.join("harness"));
+        let executor = |tool: &str| {
+            let mut adapter = adapters.resolve(tool);
+            adapter.limits = options.limits.or(adapter.limits);
+            std::fs::create_dir_all(output.join(&adapter.name))
+                .expect(" std::fs::create_dir_all failed");
+            Executor::new(adapter, harnesses.clone())
+        };
+        let executors = tools.iter().map(|tool| executor(tool)).collect();
+        let oracles = Oracle::from_specs(&options.oracle, executor);
+        let corpus = options
+            .corpus
+            .map(|dir| (dir.clone(), Corpus::from_dir(&dir)));
+        // 记录种子, 以便重新生成任意变体
+        let seed = match &corpus {
+            Some((_, corpus)) => corpus.seed(),
+            None => options.seed,
+        };
+        info!("Seed: {}", seed);
+        // 语料中的变体无法重建
+        if options.minimize && corpus.is_some() {
+            warn!("Minimization is not supported on a corpus");
+        }
         std::fs::create_dir_all(&output).expect(" std::fs::create_dir_all failed");
+        std::fs::write(output.join("seed.txt"), seed.to_string()).expect("std::fs::write failed");
         Evaluator {
-            executor: Executor::new(tool, harness),
-            config: Config::new(config, length, depth),
-            targets,
+            executors,
+            // 语料自带 testcase 和变体, 无需加载配置
+            config: corpus.is_none().then(|| {
+                Config::new(
+                    config,
+                    options.length,
+                    options.depth,
+                    options.seed,
+                    options.exhaustive,
+                )
+            }),
+            minimize: options.minimize && corpus.is_none(),
+            harnesses,
+            keep_harness: options.keep_harness,
+            baseline: options.baseline,
+            update_baseline: options.update_baseline,
+            junit: options.junit,
+            sarif: options.sarif,
+            oracles,
+            corpus,
+            selection,
             output,
         }
     }
 
-    pub fn main(&self) {
-        // 确保 targets 的生命周期独立
-        let targets = if self.targets.is_empty() {
-            (0..self.config.testcases.len()).collect()
-        } else {
-            self.targets.clone()
-        };
+    /// Configuration of the generated variants; only used without a corpus
+    fn config(&self) -> &Config {
+        self.config
+            .as_ref()
+            .expect("No configuration is loaded with a corpus")
+    }
+
+    /// Indices of the selected testcases, in the corpus if any
+    fn targets(&self) -> Vec<usize> {
+        match &self.corpus {
+            Some((_, corpus)) => {
+                let filter = self.selection.filter();
+                corpus
+                    .testcases
+                    .iter()
+                    .filter(|t| filter.matches(t.idx, &t.testcase))
+                    .map(|t| t.idx)
+                    .collect()
+            }
+            None => self.config().targets(&self.selection),
+        }
+    }
+
+    /// Evaluate the selected testcases; returns false if the run has misses or false alarms
+    /// which are not in the baseline
+    pub fn main(&self) -> bool {
+        let targets = self.targets();
+        // 穷举模式下先报告组合数
+        if let Some(config) = self.config.as_ref().filter(|config| config.exhaustive) {
+            let counts: Vec<_> = targets
+                .par_iter()
+                .map(|&idx| {
+                    let testcase = &config.testcases[idx];
+                    (testcase.id(), config.generator(testcase).count())
+                })
+                .collect();
+            for (id, count) in counts.iter() {
+                println!("{}: {} variants", id, count);
+            }
+            println!(
+                "Total: {} variants",
+                counts.iter().map(|(_, count)| count).sum::<u128>()
+            );
+        }
         // 并行处理每个任务
-        let summaries: Vec<_> = targets
+        let evaluations: Vec<_> = targets
             .par_iter() // 使用并行迭代器
             .map(|&idx| self.evaluate_one(idx))
             .collect();
 
-        let report = EvalReport::report(self.executor.name(), &summaries);
+        // 每个工具一行报告
+        let reports: Vec<_> = self
+            .executors
+            .iter()
+            .enumerate()
+            .map(|(i, executor)| {
+                let summaries: Vec<_> = evaluations
+                    .iter()
+                    .map(|(summaries, _)| summaries[i].clone())
+                    .collect();
+                // 写入结果
+                utils::serialize_to_csv(
+                    &summaries,
+                    self.output.join(executor.name()).join("EvalSummary.csv"),
+                )
+                .unwrap();
+                EvalReport::report(executor.name(), &summaries)
+            })
+            .collect();
+
+        // 按 SP/UB/TY 标签和分节分组的报告
+        let categories = self.categories(&targets, &evaluations);
+        utils::serialize_table(&categories, self.output.join("EvalCategory.csv")).unwrap();
 
-        // 写入结果
-        utils::serialize_to_csv(&summaries, self.output.join("EvalSummary.csv")).unwrap();
+        // 写入每个变体在各工具下的结果
+        let tools: Vec<_> = self.executors.iter().map(Executor::name).collect();
+        let rows: Vec<_> = evaluations.iter().flat_map(|(_, rows)| rows).collect();
+        utils::serialize_matrix(&tools, &rows, self.output.join("EvalMatrix.csv")).unwrap();
+
+        // 导出 JUnit XML 和 SARIF
+        for (i, tool) in tools.iter().enumerate() {
+            let dir = self.output.join(tool);
+            if self.junit {
+                std::fs::write(
+                    dir.join("EvalResults.xml"),
+                    export::junit(tool, i, &rows, &dir),
+                )
+                .expect("std::fs::write failed");
+            }
+            if self.sarif {
+                let file = std::fs::File::create(dir.join("EvalResults.sarif"))
+                    .expect("std::fs::File::create failed");
+                serde_json::to_writer_pretty(file, &export::sarif(tool, i, &rows, &dir))
+                    .expect("SARIF serialization failed");
+            }
+        }
+
+        // 将每个变体的结果归因于其链中的每个 flow
+        let flows = FlowReport::report(&tools, &rows);
+        utils::serialize_table(&flows, self.output.join("FlowReport.csv")).unwrap();
+
+        // 按嵌套深度和序列长度统计的检测率
+        let curves = CurveReport::report(&tools, &rows);
+        utils::serialize_table(&curves, self.output.join("RobustnessCurve.csv")).unwrap();
+        let svg = curve::svg(&tools, &rows);
+        std::fs::write(self.output.join("RobustnessCurve.svg"), &svg)
+            .expect("std::fs::write failed");
+
+        // 可移植的 HTML 报告, 链接到各变体的源码和工具输出
+        let mut sections = vec![(
+            String::from("Summary"),
+            format!(
+                "{}<pre>{}</pre>\n",
+                report::table(&reports),
+                utils::escape(LEGEND)
+            ),
+        )];
+        for kind in CATEGORIES {
+            let rows: Vec<_> = categories.iter().filter(|r| r.kind == kind).collect();
+            sections.push((format!("By {}", kind), report::table(&rows)));
+        }
+        sections.push((
+            String::from("By flow"),
+            format!(
+                "{}<pre>{}</pre>\n",
+                report::table(&flows),
+                utils::escape(FLOW_LEGEND)
+            ),
+        ));
+        sections.push((
+            String::from("By nesting depth and sequence length"),
+            format!("{}{}", report::table(&curves), svg),
+        ));
+        std::fs::write(
+            self.output.join("index.html"),
+            report::html(&sections, &tools, &rows),
+        )
+        .expect("std::fs::write failed");
 
         // 写入报告
-        println!("{}", Table::new(vec![report]));
+        utils::serialize_table(&reports, self.output.join("EvalReport.csv")).unwrap();
+        println!("{}", Table::new(reports));
+        println!("{}", LEGEND);
+        for kind in CATEGORIES {
+            let rows = categories.iter().filter(|report| report.kind == kind);
+            println!("{}", Table::new(rows));
+        }
+        println!("{}", Table::new(flows));
+        println!("{}", FLOW_LEGEND);
+        println!("{}", Table::new(curves));
+        let passed = self.compare_baseline(&tools, &rows);
+
+        if !self.keep_harness {
+            self.harnesses.cleanup();
+        }
+        passed
     }
 
-    pub(crate) fn evaluate_one(&self, idx: usize) -> EvalSummary {
-        if idx >= self.config.testcases.len() {
-            error!(
-                "Error: Index {} is out of bounds. Valid range is 0-{}",
-                idx,
-                self.config.testcases.len() - 1
-            );
-            std::process::exit(1);
-        } else {
-            utils::generate_harness(self.executor.harness.join(format!("harness-{}", idx)));
-            self.evaluate(idx)
+    /// Print the new regressions and the newly fixed cases against the baseline, if any
+    fn compare_baseline(&self, tools: &[String], rows: &[&EvalRow]) -> bool {
+        let Some(path) = &self.baseline else {
+            return true;
+        };
+        let reports = match path.exists() {
+            true => Baseline::from_file(path).compare(tools, rows, &self.output),
+            // 首次更新时基线尚不存在
+            false if self.update_baseline => Vec::new(),
+            false => panic!("Baseline not found: {}", path.display()),
+        };
+        utils::serialize_table(&reports, self.output.join("BaselineReport.csv")).unwrap();
+        let regressions = reports.iter().filter(|r| r.kind.is_regression()).count();
+        println!(
+            "Baseline {}: {} new regressions, {} newly fixed",
+            path.display(),
+            regressions,
+            reports.len() - regressions
+        );
+        if !reports.is_empty() {
+            println!("{}", Table::new(reports));
         }
+        if self.update_baseline {
+            Baseline::of(tools, rows, &self.output).to_file(path);
+            println!("Baseline updated: {}", path.display());
+            return true;
+        }
+        regressions == 0
     }
 
-    pub(crate) fn evaluate(&self, idx: usize) -> EvalSummary {
-        let process = |expr: &Expr, (pos, neg): (Program, Program)| -> EvalResults {
-            // 写入文件
-            info!(
-                "Write testcase-{:03} with expression-{} into file system",
-                idx, &expr.num
+    /// Check the labels of every variant with the oracle, independently of any tool; returns
+    /// false if any variant has an invalid ground truth
+    pub fn verify(&self) -> bool {
+        assert!(
+            !self.oracles.is_empty(),
+            "No oracle to verify the variants with"
+        );
+        let rows: Vec<TruthRow> = self
+            .targets()
+            .par_iter()
+            .map(|&idx| self.verify_one(idx))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect();
+        utils::serialize_truths(&rows, self.output.join("GroundTruth.csv")).unwrap();
+
+        let mut reports: Vec<TruthReport> = Vec::new();
+        for row in rows.iter() {
+            if reports.last().is_none_or(|report| report.id != row.id) {
+                reports.push(TruthReport {
+                    id: row.id.clone(),
+                    ..Default::default()
+                });
+            }
+            reports.last_mut().unwrap().count(row.truth);
+        }
+        println!("{}", Table::new(&reports));
+        let invalid: Vec<_> = rows
+            .iter()
+            .filter(|row| row.truth == Some(GroundTruth::Invalid))
+            .collect();
+        for row in invalid.iter() {
+            println!(
+                "Invalid ground truth: {} with expression-{} (POS {:?}, NEG {:?})",
+                row.id,
+                row.variant,
+                row.pos.unwrap(),
+                row.neg.unwrap()
             );
+        }
+
+        if !self.keep_harness {
+            self.harnesses.cleanup();
+        }
+        invalid.is_empty()
+    }
+
+    fn verify_one(&self, idx: usize) -> Vec<TruthRow> {
+        let testcase = self.testcase(idx);
+        let id = testcase.id();
+        // 没有 oracle 能观察该类别时, 真值无法确定
+        let oracle = self.oracle(testcase.kind());
+        let mut rows = Vec::new();
+        self.variants(idx, |parent, num, chain, (pos, neg)| {
+            if let Some(oracle) = oracle {
+                let dir = self.output.join(oracle.name()).join(id).join(num);
+                utils::write(dir, (&pos, &neg));
+            }
+            let (pos, neg, truth) = match (oracle, self.compiles((&pos, &neg))) {
+                (_, false) => (None, None, None),
+                (Some(oracle), true) => {
+                    let (pos, neg) = oracle.verify((&pos, &neg));
+                    (Some(pos), Some(neg), Some(GroundTruth::of(pos, neg)))
+                }
+                (None, true) => (
+                    Some(Behaviour::Inconclusive),
+                    Some(Behaviour::Inconclusive),
+                    Some(GroundTruth::Inconclusive),
+                ),
+            };
+            rows.push(TruthRow {
+                idx,
+                id: id.to_string(),
+                parent: parent.map(str::to_string),
+                variant: num.to_string(),
+                chain: chain.to_vec(),
+                pos,
+                neg,
+                truth,
+            });
+        });
+        rows
+    }
+
+    /// Visit the variants of testcase `idx` with their parent, number and flows: the ones of the
+    /// corpus if any, or else the ones generated independently of any tool verdict
+    ///
+    /// These are the variants evaluated by [`Evaluator::evaluate`] with the same seed, which only
+    /// skips the descendants of the variants that no tool detects robustly.
+    fn variants<F>(&self, idx: usize, mut visit: F)
+    where
+        F: FnMut(Option<&str>, &str, &[String], (Program, Program)),
+    {
+        if let Some((dir, corpus)) = &self.corpus {
+            let testcase = corpus.testcase(idx);
+            for variant in testcase.variants.iter() {
+                let programs = variant.programs(dir, &testcase.testcase);
+                visit(
+                    variant.parent.as_deref(),
+                    &variant.num,
+                    &variant.chain,
+                    programs,
+                );
+            }
+            return;
+        }
+        let testcase = &self.config().testcases[idx];
+        let src = Expr::source();
+        let programs = testcase.into_programs(&src, self.config().seed);
+        visit(None, &src.num, &src.chain, programs);
+        let mut visit = |Variant { parent, expr }| {
+            let programs = testcase.into_programs(&expr, self.config().seed);
+            visit(Some(&parent), &expr.num, &expr.chain, programs);
+        };
+        let generator = self.config().generator(testcase);
+        match self.config().exhaustive {
+            true => generator.exhaustive(&mut visit),
+            false => generator.random(&mut self.config().rng(testcase), &mut visit),
+        }
+    }
+
+    /// Reports of every tool on the testcases of each category
+    fn categories(
+        &self,
+        targets: &[usize],
+        evaluations: &[(Vec<EvalSummary>, Vec<EvalRow>)],
+    ) -> Vec<CategoryReport> {
+        let mut reports = Vec::new();
+        for kind in CATEGORIES {
+            // 类别 -> 该类别的 testcase 在 evaluations 中的位置
+            let mut groups: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+            for (j, &idx) in targets.iter().enumerate() {
+                for (_, name) in self
+                    .testcase(idx)
+                    .categories()
+                    .into_iter()
+                    .filter(|(k, _)| *k == kind)
+                {
+                    groups.entry(name).or_default().push(j);
+                }
+            }
+            for (name, group) in groups {
+                for (i, executor) in self.executors.iter().enumerate() {
+                    let summaries: Vec<_> =
+                        group.iter().map(|&j| evaluations[j].0[i].clone()).collect();
+                    reports.push(CategoryReport {
+                        kind: kind.to_string(),
+                        name: name.to_string(),
+                        report: EvalReport::report(executor.name(), &summaries),
+                    });
+                }
+            }
+        }
+        reports
+    }
+
+    pub(crate) fn evaluate_one(&self, idx: usize) -> (Vec<EvalSummary>, Vec<EvalRow>) {
+        let evaluation = match &self.corpus {
+            Some((dir, corpus)) => self.evaluate_corpus(dir, corpus, idx),
+            None => self.evaluate(idx),
+        };
+        for (executor, tree) in self.executors.iter().zip(&evaluation.trees) {
+            let output = self
+                .output
+                .join(executor.name())
+                .join(self.testcase(idx).id());
+            tree.to_json(output.clone()).unwrap();
+            // Graphviz 缺失时只警告一次, 评估树仍见于 evalTree.json 和 HTML 报告
+            if let Err(err) =
+                utils::generate_image_from_dot(&tree.to_dot(), output.join("evalTree.png"))
+            {
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| warn!("No image of the evaluation trees: {}", err));
+            }
+        }
+        (evaluation.summaries, evaluation.rows)
+    }
+
+    /// Evaluate a variant with every tool
+    pub(crate) fn process(
+        &self,
+        idx: usize,
+        num: &str,
+        (pos, neg): (Program, Program),
+    ) -> Vec<EvalResults> {
+        // 写入文件
+        let id = self.testcase(idx).id();
+        info!("Write {} with expression-{} into file system", id, num);
+        for executor in self.executors.iter() {
             utils::write(
-                self.output
-                    .join(format!("testcase-{:03}", idx))
-                    .join(&expr.num),
+                self.output.join(executor.name()).join(id).join(num),
                 (&pos, &neg),
             );
+        }
+
+        // 变体无法编译时不调用工具, 避免将生成器的问题计入工具
+        if !self.compiles((&pos, &neg)) {
+            info!("{} with expression-{} does not compile", id, num);
+            let res = EvalResults(EvalResult::CompileError, EvalResult::CompileError);
+            return vec![res; self.executors.len()];
+        }
+
+        // 行为与标签不符的变体不调用工具, 避免错误的真值计入工具
+        if let Some(oracle) = self.oracle(pos.kind()) {
+            let (pos, neg) = oracle.verify((&pos, &neg));
+            if GroundTruth::of(pos, neg) == GroundTruth::Invalid {
+                warn!(
+                    "{} with expression-{} has an invalid ground truth: POS {:?}, NEG {:?}",
+                    id, num, pos, neg
+                );
+                let res = EvalResults(EvalResult::InvalidTruth, EvalResult::InvalidTruth);
+                return vec![res; self.executors.len()];
+            }
+        }
+
+        // 每个工具依次执行评估, 并保存其输出
+        self.executors
+            .iter()
+            .map(|executor| {
+                let executions = (executor.execute(&pos), executor.execute(&neg));
+                let dir = self.output.join(executor.name()).join(id).join(num);
+                utils::write_execution(&dir, "POS", &executions.0, &pos).unwrap();
+                utils::write_execution(&dir, "NEG", &executions.1, &neg).unwrap();
+                utils::evaluate(executions, (&pos, &neg))
+            })
+            .collect()
+    }
+
+    /// Oracle of the testcases of `kind`, if any
+    fn oracle(&self, kind: Kind) -> Option<&Oracle> {
+        self.oracles.iter().find(|oracle| oracle.observes(kind))
+    }
+
+    /// Check if both programs compile
+    pub(crate) fn compiles(&self, (pos, neg): (&Program, &Program)) -> bool {
+        self.harnesses.check(pos) && self.harnesses.check(neg)
+    }
 
-            // 执行评估
-            let outputs = (
-                self.executor.execute(idx, pos),
-                self.executor.execute(idx, neg),
+    /// Minimize the variant `expr` for every tool failing on it, and write the reproducers to
+    /// `minimized/{num}` in the testcase directory of the tool
+    ///
+    /// With an oracle, a reduction is only kept if its ground truth is not invalid.
+    pub(crate) fn minimize(
+        &self,
+        evaluation: &mut Evaluation,
+        testcase: &Testcase,
+        parent: &str,
+        expr: &Expr,
+        results: &[EvalResults],
+    ) {
+        let Some(parent) = evaluation.results(parent).map(<[_]>::to_vec) else {
+            return;
+        };
+        for (i, executor) in self.executors.iter().enumerate() {
+            let Some(failure) = Failure::of(&parent[i], &results[i]) else {
+                continue;
+            };
+            let recipe = minimizer::minimize(&expr.recipe, |recipe| {
+                let expr = recipe.build(0, &self.config().flows, testcase);
+                let (pos, neg) = testcase.into_programs(&expr, self.config().seed);
+                // 缩减不能去除缺陷本身, 否则真值无效
+                self.compiles((&pos, &neg))
+                    && self.oracle(pos.kind()).is_none_or(|oracle| {
+                        let (pos, neg) = oracle.verify((&pos, &neg));
+                        GroundTruth::of(pos, neg) != GroundTruth::Invalid
+                    })
+                    && failure.holds(&executor.evaluate((&pos, &neg)))
+            });
+            info!(
+                "Minimize {} with expression-{} for {}: {} -> {} flows",
+                testcase.id(),
+                expr.num,
+                executor.name(),
+                expr.recipe.size(),
+                recipe.size()
+            );
+            // 相同的最小变体只写入一次
+            if evaluation.minimized[i].insert(recipe.clone()) {
+                let minimal = recipe.build(0, &self.config().flows, testcase);
+                let (pos, neg) = testcase.into_programs(&minimal, self.config().seed);
+                utils::write(
+                    self.output
+                        .join(executor.name())
+                        .join(testcase.id())
+                        .join("minimized")
+                        .join(&expr.num),
+                    (&pos, &neg),
+                );
+            }
+        }
+    }
+
+    /// Testcase `idx`, from the corpus if any
+    pub(crate) fn testcase(&self, idx: usize) -> &Testcase {
+        match &self.corpus {
+            Some((_, corpus)) => &corpus.testcase(idx).testcase,
+            None => &self.config().testcases[idx],
+        }
+    }
+
+    /// Evaluate the variants of the corpus testcase `idx`, in the order they were generated
+    pub(crate) fn evaluate_corpus(&self, dir: &Path, corpus: &Corpus, idx: usize) -> Evaluation {
+        let testcase = corpus.testcase(idx);
+        let mut evaluation = Evaluation::new(idx, testcase.testcase.id(), self.executors.len());
+        for variant in testcase.variants.iter() {
+            let programs = variant.programs(dir, &testcase.testcase);
+            let results = self.process(idx, &variant.num, programs);
+            evaluation.record(
+                variant.parent.as_deref(),
+                &variant.num,
+                variant.shape(),
+                &variant.chain,
+                &results,
             );
+        }
+        evaluation
+    }
 
-            utils::evaluate(outputs)
+    /// Evaluate the variants of testcase `idx` with every tool
+    ///
+    /// Without `exhaustive`, only the descendants of the variants that some tool detects robustly
+    /// (TP and TN) are evaluated.
+    pub(crate) fn evaluate(&self, idx: usize) -> Evaluation {
+        // 任一工具鲁棒检测时继续嵌套
+        let is_robust = |results: &[EvalResults]| {
+            results
+                .iter()
+                .any(|res| matches!(res, EvalResults(EvalResult::TP, EvalResult::TN)))
         };
 
         // 获取要评估的 testcase
-        let testcase = &self.config.testcases[idx];
-        // 初始化 EvalSummary 和 Eval EvalTree
-        let mut summary = EvalSummary::new(idx);
-        let mut tree = EvalTree::new();
+        let testcase = &self.config().testcases[idx];
+        // 初始化 EvalSummary 和 Eval EvalTree (每个工具一份)
+        let mut evaluation = Evaluation::new(idx, testcase.id(), self.executors.len());
 
         // 评估 testcase
         let src_expr = Expr::source();
-        let programs = testcase.into_programs(&src_expr.code);
-        let res = process(&src_expr, programs);
-        summary.count(&res);
+        let programs = testcase.into_programs(&src_expr, self.config().seed);
+        let results = self.process(idx, &src_expr.num, programs);
+        evaluation.record(None, &src_expr.num, (0, 0), &src_expr.chain, &results);
 
-        // BFS 遍历所有可行的 flow 的组合方案
-        let root = EvalNode::new(&src_expr.num, res);
-        tree.set_root(root);
         // 评估嵌套 flow 后的 testcase
-        if let EvalResults(EvalResult::TP, EvalResult::TN) = res {
-            // exprs 初始化
-            let mut exprs = Exprs::new();
-            exprs.push(Expr::source());
-
-            // sources 队列
-            let mut sources = VecDeque::new();
-            sources.push_back(Expr::source());
-            while !sources.is_empty() {
-                let src = sources.pop_front().unwrap();
-                for flow in self.config.flows.iter() {
-                    let expr = flow.into_expr(tree.count_nodes(), &src, &exprs, testcase);
-                    let programs = testcase.into_programs(&expr.code);
-                    let res = process(&expr, programs);
-                    summary.count(&res); // 统计
-                    tree.add_child(&src.num, &expr.num, res).unwrap(); // 插入评估树
-
-                    if let EvalResults(EvalResult::TP, EvalResult::TN) = res {
-                        if expr.length < self.config.length && expr.depth < self.config.depth {
-                            sources.push_back(expr.clone());
-                            exprs.push(expr);
-                        }
-                    }
+        if self.config().exhaustive {
+            // 与工具的结果无关, 评估所有组合
+            let generator = self.config().generator(testcase);
+            generator.exhaustive(|Variant { parent, expr }| {
+                let programs = testcase.into_programs(&expr, self.config().seed);
+                let results = self.process(idx, &expr.num, programs);
+                if self.minimize {
+                    self.minimize(&mut evaluation, testcase, &parent, &expr, &results);
                 }
+                let shape = (expr.length, expr.depth);
+                evaluation.record(Some(&parent), &expr.num, shape, &expr.chain, &results);
+            });
+        } else if is_robust(&results) {
+            // 与 verify 和 generate 使用同一生成器, 变体的编号和内容与工具的结果无关;
+            // 仅评估鲁棒检测的变体的子变体, 其余子变体被跳过
+            let mut robust = HashSet::from([src_expr.num.clone()]);
+            // 每个 testcase 使用独立的随机数生成器, 结果不受 rayon 调度影响
+            let generator = self.config().generator(testcase);
+            generator.random(
+                &mut self.config().rng(testcase),
+                |Variant { parent, expr }| {
+                    if !robust.contains(&parent) {
+                        return;
+                    }
+                    let programs = testcase.into_programs(&expr, self.config().seed);
+                    let results = self.process(idx, &expr.num, programs);
+                    if self.minimize {
+                        self.minimize(&mut evaluation, testcase, &parent, &expr, &results);
+                    }
+                    let shape = (expr.length, expr.depth);
+                    evaluation.record(Some(&parent), &expr.num, shape, &expr.chain, &results);
+                    if is_robust(&results) {
+                        robust.insert(expr.num);
+                    }
+                },
+            );
+        }
+        evaluation
+    }
+}
+
+/// Summaries, evaluation trees and matrix rows of one testcase (one per tool)
+pub(crate) struct Evaluation {
+    idx: usize,
+    id: String,
+    summaries: Vec<EvalSummary>,
+    trees: Vec<EvalTree>,
+    rows: Vec<EvalRow>,
+    /// Position of each variant in `rows`
+    positions: HashMap<String, usize>,
+    /// Minimized recipes of each tool
+    minimized: Vec<HashSet<Recipe>>,
+}
+
+impl Evaluation {
+    pub(crate) fn new(idx: usize, id: &str, tools: usize) -> Self {
+        Evaluation {
+            idx,
+            id: id.to_string(),
+            summaries: (0..tools).map(|_| EvalSummary::new(idx, id)).collect(),
+            trees: (0..tools).map(|_| EvalTree::new()).collect(),
+            rows: Vec::new(),
+            positions: HashMap::new(),
+            minimized: vec![HashSet::new(); tools],
+        }
+    }
+
+    /// Results of the recorded variant `num`
+    pub(crate) fn results(&self, num: &str) -> Option<&[EvalResults]> {
+        let pos = self.positions.get(num)?;
+        Some(&self.rows[*pos].results)
+    }
+
+    /// Record the results of the variant `num` of sequence `length` and nesting `depth`,
+    /// nested in `parent` (`None` for the root) through the flows of `chain`
+    pub(crate) fn record(
+        &mut self,
+        parent: Option<&str>,
+        num: &str,
+        (length, depth): (usize, usize),
+        chain: &[String],
+        results: &[EvalResults],
+    ) {
+        for ((summary, tree), res) in self
+            .summaries
+            .iter_mut()
+            .zip(self.trees.iter_mut())
+            .zip(results)
+        {
+            summary.count(res); // 统计
+            match parent {
+                Some(parent) => tree.add_child(parent, num, *res, chain).unwrap(), // 插入评估树
+                None => tree.set_root(EvalNode::new(num, *res)),
             }
         }
-        tree.to_json(self.output.join(format!("testcase-{:03}", idx))).unwrap();
-        utils::generate_image_from_dot(
-            &tree.to_dot(),
-            self.output
-                .join(format!("testcase-{:03}", idx))
-                .join("evalTree.png"),
-        )
-        .unwrap();
-        return summary;
+        self.positions.insert(num.to_string(), self.rows.len());
+        self.rows.push(EvalRow {
+            idx: self.idx,
+            id: self.id.clone(),
+            parent: parent.map(str::to_string),
+            variant: num.to_string(),
+            length,
+            depth,
+            chain: chain.to_vec(),
+            results: results.to_vec(),
+        });
     }
 }
 
 pub(crate) struct Executor {
-    tool: PathBuf,
-    harness: PathBuf,
+    adapter: Adapter,
+    harnesses: Harnesses,
 }
 
 impl Executor {
-    pub(crate) fn new(tool: PathBuf, harness: PathBuf) -> Self {
-        std::fs::create_dir_all(&harness).expect(" std::fs::create_dir_all failed");
-        Executor { tool, harness }
+    pub(crate) fn new(adapter: Adapter, harnesses: Harnesses) -> Self {
+        Executor { adapter, harnesses }
     }
 
     pub(crate) fn name(&self) -> String {
-        self.tool
-            .file_stem()
-            .unwrap()
-            .to_os_string()
-            .into_string()
-            .unwrap()
+        self.adapter.name.clone()
+    }
+
+    /// Execute the tool on both programs, and compare the findings with their sinks
+    pub(crate) fn evaluate(&self, (pos, neg): (&Program, &Program)) -> EvalResults {
+        let outputs = (self.execute(pos), self.execute(neg));
+        utils::evaluate(outputs, (pos, neg))
     }
 
-    pub(crate) fn execute(&self, idx: usize, program: Program) -> Output {
-        program.into_harness(&self.harness.join(format!("harness-{}", idx)));
-        Command::new(&self.tool)
-            .arg(&self.harness.join(format!("harness-{}", idx)))
-            .output()
-            .expect("Tool failed to execute")
+    pub(crate) fn execute(&self, program: &Program) -> Execution {
+        let harness = self.harnesses.worker();
+        program.into_harness(&harness.dir);
+        let target_dir = harness.target_dir(self.adapter.target, &self.adapter.name);
+        let command = self.adapter.command(&harness.dir, target_dir.as_deref());
+        let (output, status, elapsed) = utils::run(command, &self.adapter.limits);
+        let findings = self.adapter.parse(&output);
+        Execution {
+            status,
+            elapsed,
+            success: self.adapter.is_success(&output, &findings),
+            findings,
+            output,
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct EvalResults(EvalResult, EvalResult);
 
-impl Serialize for EvalResults {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let serialized_value = match (self.0, self.1) {
+impl EvalResults {
+    /// Check if the variant is excluded from the evaluation of the tools
+    pub(crate) fn is_invalid(&self) -> bool {
+        matches!(
+            self,
+            EvalResults(EvalResult::CompileError | EvalResult::InvalidTruth, _)
+                | EvalResults(_, EvalResult::CompileError | EvalResult::InvalidTruth)
+        )
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match (self.0, self.1) {
+            (EvalResult::CompileError, _) | (_, EvalResult::CompileError) => "Compile Error",
+            (EvalResult::InvalidTruth, _) | (_, EvalResult::InvalidTruth) => "Invalid Ground Truth",
+            (EvalResult::Timeout, _) | (_, EvalResult::Timeout) => "Timeout",
+            (EvalResult::OutOfMemory, _) | (_, EvalResult::OutOfMemory) => "Out of Memory",
             (EvalResult::Err, _) | (_, EvalResult::Err) => "Error",
             (EvalResult::TP, EvalResult::TN) => "True Positive & Negative",
             (EvalResult::TP, EvalResult::FP) => "False Positive",
             (EvalResult::FN, EvalResult::FP) => "False Positive & Negative",
             (EvalResult::FN, EvalResult::TN) => "False Negative",
             _ => unreachable!(),
-        };
+        }
+    }
+}
 
-        serializer.serialize_str(serialized_value)
+impl Serialize for EvalResults {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
     }
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub(crate) enum EvalResult {
-    Err, // 工具执行出错
+    Err,          // 工具执行出错
+    Timeout,      // 工具执行超时
+    OutOfMemory,  // 工具内存溢出
+    CompileError, // 变体无法编译, 不计入工具的评估
+    InvalidTruth, // 变体的行为与标签不符, 不计入工具的评估
     TP,
     FP, // 误报
     FN, // 漏报
     TN,
 }
 
-#[derive(serde::Serialize)]
+/// Results of all tools on one variant
+pub(crate) struct EvalRow {
+    idx: usize,
+    id: String,
+    /// `None` for the original testcase
+    parent: Option<String>,
+    variant: String,
+    /// Sequence length of the expression
+    length: usize,
+    /// Nesting depth of the expression
+    depth: usize,
+    /// Flows the source is nested in, from the innermost
+    chain: Vec<String>,
+    results: Vec<EvalResults>,
+}
+
+#[cfg(test)]
+impl EvalRow {
+    /// Variant of `sp-system-01` nested through the flows of `chain`, with the result of one tool
+    pub(crate) fn test(
+        variant: &str,
+        parent: Option<&str>,
+        chain: &[&str],
+        results: EvalResults,
+    ) -> Self {
+        EvalRow {
+            idx: 0,
+            id: String::from("sp-system-01"),
+            parent: parent.map(str::to_string),
+            variant: variant.to_string(),
+            length: chain.len(),
+            depth: 0,
+            chain: chain.iter().map(|flow| flow.to_string()).collect(),
+            results: vec![results],
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
 pub(crate) struct EvalSummary {
     #[serde(
         rename = "编号",
         serialize_with = "EvalSummary::format_with_leading_zeros"
     )]
     idx: usize,
+    #[serde(rename = "ID")]
+    id: String,
     #[serde(rename = "变体")]
     variant_count: usize,
     #[serde(rename = "RD")]
@@ -238,13 +994,20 @@ pub(crate) struct EvalSummary {
     tn_count: usize,
     #[serde(rename = "ER")]
     err_count: usize,
+    #[serde(rename = "TO")]
+    timeout_count: usize,
+    #[serde(rename = "OM")]
+    oom_count: usize,
+    #[serde(rename = "IV")]
+    invalid_count: usize,
 }
 
 impl EvalSummary {
     /// Init Eval Summary (All are 0)
-    pub(crate) fn new(idx: usize) -> Self {
+    pub(crate) fn new(idx: usize, id: &str) -> Self {
         EvalSummary {
             idx,
+            id: id.to_string(),
             variant_count: 0,
             robust_count: 0,
             tp_count: 0,
@@ -252,20 +1015,32 @@ impl EvalSummary {
             fn_count: 0,
             tn_count: 0,
             err_count: 0,
+            timeout_count: 0,
+            oom_count: 0,
+            invalid_count: 0,
         }
     }
 
     /// Count based on res enumeration
     pub(crate) fn count(&mut self, res: &EvalResults) {
+        // 无法编译或真值无效的变体单独统计
+        if res.is_invalid() {
+            self.invalid_count += 1;
+            return;
+        }
         self.variant_count += 1;
         match res.0 {
             EvalResult::Err => self.err_count += 1,
+            EvalResult::Timeout => self.timeout_count += 1,
+            EvalResult::OutOfMemory => self.oom_count += 1,
             EvalResult::TP => self.tp_count += 1,
             EvalResult::FN => self.fn_count += 1,
             _ => unreachable!(), // POS Case 不存在 TN 和 FP
         }
         match res.1 {
             EvalResult::Err => self.err_count += 1,
+            EvalResult::Timeout => self.timeout_count += 1,
+            EvalResult::OutOfMemory => self.oom_count += 1,
             EvalResult::FP => self.fp_count += 1,
             EvalResult::TN => self.tn_count += 1,
             _ => unreachable!(), // NEG Case 不存在 TP 和 FN
@@ -275,6 +1050,53 @@ impl EvalSummary {
         }
     }
 
+    /// Sum of the counts of `summaries`
+    pub(crate) fn total(summaries: &[EvalSummary]) -> Self {
+        let mut total = EvalSummary::new(0, "");
+        for s in summaries {
+            total.variant_count += s.variant_count;
+            total.robust_count += s.robust_count;
+            total.tp_count += s.tp_count;
+            total.fn_count += s.fn_count;
+            total.fp_count += s.fp_count;
+            total.tn_count += s.tn_count;
+            total.err_count += s.err_count;
+            total.timeout_count += s.timeout_count;
+            total.oom_count += s.oom_count;
+            total.invalid_count += s.invalid_count;
+        }
+        total
+    }
+
+    pub(crate) fn precision(&self) -> Option<f64> {
+        ratio(self.tp_count, self.tp_count + self.fp_count)
+    }
+
+    pub(crate) fn recall(&self) -> Option<f64> {
+        ratio(self.tp_count, self.tp_count + self.fn_count)
+    }
+
+    /// `2TP / (2TP + FP + FN)`, the harmonic mean of precision and recall
+    pub(crate) fn f1(&self) -> Option<f64> {
+        ratio(
+            2 * self.tp_count,
+            2 * self.tp_count + self.fp_count + self.fn_count,
+        )
+    }
+
+    /// Correct verdicts among the POS and NEG programs on which the tool finished
+    pub(crate) fn accuracy(&self) -> Option<f64> {
+        ratio(
+            self.tp_count + self.tn_count,
+            self.tp_count + self.fn_count + self.fp_count + self.tn_count,
+        )
+    }
+
+    /// Robust detections among the valid variants
+    pub(crate) fn robustness(&self) -> Option<f64> {
+        ratio(self.robust_count, self.variant_count)
+    }
+
     // Custom function to serialize numbers with leading zeros
     pub(crate) fn format_with_leading_zeros<S>(
         num: &usize,
@@ -288,6 +1110,58 @@ impl EvalSummary {
     }
 }
 
+fn ratio(num: usize, den: usize) -> Option<f64> {
+    (den != 0).then(|| num as f64 / den as f64)
+}
+
+/// `rate` as a percentage, or `-` when undefined
+pub(crate) fn percent(rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) => format!("{:.1}%", rate * 100.0),
+        None => String::from("-"),
+    }
+}
+
+/// Meaning of the columns of [`EvalReport`], printed below the reports
+pub(crate) const LEGEND: &str = "\
+RD ... IV: testcases with at least one such verdict (testcases where every variant has it)
+P, R, F1, ACC, RD/V: micro average over all variants (macro average over the testcases)";
+
+/// Rate of a [`EvalReport`], micro-averaged over all variants and macro-averaged over the
+/// testcases; `None` when undefined, e.g. the precision of a tool which reports nothing
+#[derive(Default)]
+pub(crate) struct Rate {
+    micro: Option<f64>,
+    macro_average: Option<f64>,
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 与 Metric 一致, 格式为 "micro (macro)"
+        write!(
+            f,
+            "{} ({})",
+            percent(self.micro),
+            percent(self.macro_average)
+        )
+    }
+}
+
+impl Rate {
+    pub(crate) fn of<F>(summaries: &[EvalSummary], rate: F) -> Self
+    where
+        F: Fn(&EvalSummary) -> Option<f64>,
+    {
+        // 宏平均忽略该比率无定义的 testcase
+        let rates: Vec<f64> = summaries.iter().filter_map(&rate).collect();
+        Rate {
+            micro: rate(&EvalSummary::total(summaries)),
+            macro_average: (!rates.is_empty())
+                .then(|| rates.iter().sum::<f64>() / rates.len() as f64),
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Metric {
     normal: usize,
@@ -328,6 +1202,22 @@ pub(crate) struct EvalReport {
     true_negative: Metric,
     #[tabled(rename = "错误 (ER)")]
     error: Metric,
+    #[tabled(rename = "超时 (TO)")]
+    timeout: Metric,
+    #[tabled(rename = "内存溢出 (OM)")]
+    out_of_memory: Metric,
+    #[tabled(rename = "无效变体 (IV)")]
+    invalid: Metric,
+    #[tabled(rename = "精确率 (P)")]
+    precision: Rate,
+    #[tabled(rename = "召回率 (R)")]
+    recall: Rate,
+    #[tabled(rename = "F1")]
+    f1: Rate,
+    #[tabled(rename = "准确率 (ACC)")]
+    accuracy: Rate,
+    #[tabled(rename = "鲁棒率 (RD/V)")]
+    robustness: Rate,
 }
 
 impl EvalReport {
@@ -348,11 +1238,203 @@ impl EvalReport {
             report.false_postive.count(s.fp_count, s.variant_count);
             report.true_negative.count(s.tn_count, s.variant_count);
             report.error.count(s.err_count, s.variant_count * 2);
+            report.timeout.count(s.timeout_count, s.variant_count * 2);
+            report.out_of_memory.count(s.oom_count, s.variant_count * 2);
+            report
+                .invalid
+                .count(s.invalid_count, s.variant_count + s.invalid_count);
         });
+        report.precision = Rate::of(summaries, EvalSummary::precision);
+        report.recall = Rate::of(summaries, EvalSummary::recall);
+        report.f1 = Rate::of(summaries, EvalSummary::f1);
+        report.accuracy = Rate::of(summaries, EvalSummary::accuracy);
+        report.robustness = Rate::of(summaries, EvalSummary::robustness);
         report
     }
 }
 
+/// Behaviour of one variant under the oracle
+#[derive(Serialize)]
+pub(crate) struct TruthRow {
+    #[serde(
+        rename = "编号",
+        serialize_with = "EvalSummary::format_with_leading_zeros"
+    )]
+    idx: usize,
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "变体")]
+    variant: String,
+    #[serde(rename = "父变体")]
+    parent: Option<String>,
+    #[serde(rename = "流", serialize_with = "TruthRow::serialize_chain")]
+    chain: Vec<String>,
+    /// `None` when the variant does not compile
+    #[serde(rename = "POS")]
+    pos: Option<Behaviour>,
+    #[serde(rename = "NEG")]
+    neg: Option<Behaviour>,
+    #[serde(rename = "真值")]
+    truth: Option<GroundTruth>,
+}
+
+impl TruthRow {
+    fn serialize_chain<S>(chain: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&chain.join(" > "))
+    }
+}
+
+/// Ground truth of the variants of one testcase
+#[derive(Default, Tabled)]
+pub(crate) struct TruthReport {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "变体")]
+    variants: usize,
+    #[tabled(rename = "有效")]
+    valid: usize,
+    #[tabled(rename = "无效")]
+    invalid: usize,
+    #[tabled(rename = "不确定")]
+    inconclusive: usize,
+    #[tabled(rename = "无法编译")]
+    compile_error: usize,
+}
+
+impl TruthReport {
+    fn count(&mut self, truth: Option<GroundTruth>) {
+        self.variants += 1;
+        match truth {
+            Some(GroundTruth::Valid) => self.valid += 1,
+            Some(GroundTruth::Invalid) => self.invalid += 1,
+            Some(GroundTruth::Inconclusive) => self.inconclusive += 1,
+            None => self.compile_error += 1,
+        }
+    }
+}
+
+/// Kinds of the categories of [`Testcase::categories`], in the order they are reported
+const CATEGORIES: [&str; 5] = ["SP", "UB", "TY", "Section", "Kind"];
+
+/// [`EvalReport`] restricted to the testcases of one category
+#[derive(Tabled)]
+pub(crate) struct CategoryReport {
+    #[tabled(rename = "分类")]
+    kind: String,
+    #[tabled(rename = "类别")]
+    name: String,
+    #[tabled(inline)]
+    report: EvalReport,
+}
+
+/// Verdicts of one tool on the variants whose flow chain contains `flow`
+#[derive(Default, Tabled)]
+pub(crate) struct FlowReport {
+    #[tabled(rename = "工具")]
+    tool: String,
+    #[tabled(rename = "流")]
+    flow: String,
+    #[tabled(rename = "变体")]
+    variants: usize,
+    #[tabled(rename = "鲁棒检测 (RD)")]
+    robust: usize,
+    #[tabled(rename = "漏报 (FN)")]
+    false_negative: usize,
+    #[tabled(rename = "误报 (FP)")]
+    false_positive: usize,
+    #[tabled(rename = "失效 (RD -> FN/FP)")]
+    broken: usize,
+    #[tabled(rename = "错误 (ER/TO/OM)")]
+    error: usize,
+    #[tabled(rename = "无效变体 (IV)")]
+    invalid: usize,
+}
+
+/// Legend of the [`FlowReport`] table
+pub(crate) const FLOW_LEGEND: &str = "\
+变体 ... IV: variants with the flow anywhere in their chain, counted once per flow
+RD -> FN/FP: only for the outermost flow, applied to a variant the tool detects robustly";
+
+impl FlowReport {
+    /// One report per tool and flow, in the order the flows first appear in `rows`
+    ///
+    /// A variant counts for every flow of its chain. It is `broken` for its outermost flow when
+    /// the tool detects its parent robustly but misses the bug, or reports a false positive, once
+    /// that flow is applied.
+    pub(crate) fn report(tools: &[String], rows: &[&EvalRow]) -> Vec<Self> {
+        let results: HashMap<(usize, &str), &[EvalResults]> = rows
+            .iter()
+            .map(|row| ((row.idx, row.variant.as_str()), row.results.as_slice()))
+            .collect();
+        let mut flows: Vec<&str> = Vec::new();
+        let mut reports: Vec<FlowReport> = Vec::new();
+        for row in rows {
+            let parent = row
+                .parent
+                .as_deref()
+                .and_then(|parent| results.get(&(row.idx, parent)));
+            // 原始 testcase 没有 flow, 同一 flow 在链中出现多次时只计一次
+            let mut seen = HashSet::new();
+            for (depth, flow) in row.chain.iter().enumerate().rev() {
+                if !seen.insert(flow) {
+                    continue;
+                }
+                let pos = match flows.iter().position(|f| f == flow) {
+                    Some(pos) => pos,
+                    None => {
+                        flows.push(flow);
+                        reports.extend(tools.iter().map(|tool| FlowReport {
+                            tool: tool.clone(),
+                            flow: flow.clone(),
+                            ..Default::default()
+                        }));
+                        flows.len() - 1
+                    }
+                };
+                // 仅最外层的 flow 作用于父变体
+                let outermost = depth + 1 == row.chain.len();
+                for (i, res) in row.results.iter().enumerate() {
+                    let parent = parent.filter(|_| outermost).map(|results| &results[i]);
+                    reports[pos * tools.len() + i].count(res, parent);
+                }
+            }
+        }
+        // 按工具排列, 同一工具内保持 flow 的顺序
+        reports.sort_by_key(|report| tools.iter().position(|tool| *tool == report.tool));
+        reports
+    }
+
+    fn count(&mut self, res: &EvalResults, parent: Option<&EvalResults>) {
+        let is_error = |res: &EvalResult| {
+            matches!(
+                res,
+                EvalResult::Err | EvalResult::Timeout | EvalResult::OutOfMemory
+            )
+        };
+        if res.is_invalid() {
+            self.invalid += 1;
+            return;
+        }
+        self.variants += 1;
+        if is_error(&res.0) || is_error(&res.1) {
+            self.error += 1;
+            return;
+        }
+        let missed = matches!(res.0, EvalResult::FN);
+        let misreported = matches!(res.1, EvalResult::FP);
+        self.false_negative += missed as usize;
+        self.false_positive += misreported as usize;
+        if !(missed || misreported) {
+            self.robust += 1;
+        } else if let Some(EvalResults(EvalResult::TP, EvalResult::TN)) = parent {
+            self.broken += 1;
+        }
+    }
+}
+
 pub(crate) struct Exprs(Vec<Expr>);
 
 impl Exprs {
@@ -361,11 +1443,10 @@ impl Exprs {
     }
 
     /// 随机返回 `Exprs` 实例中一个 `Expr` 的共享引用
-    pub(crate) fn random_expr(&self) -> Option<&Expr> {
+    pub(crate) fn random_expr<R: Rng>(&self, rng: &mut R) -> Option<&Expr> {
         if self.0.is_empty() {
             None // 如果没有任何元素，返回 None
         } else {
-            let mut rng = rand::thread_rng();
             let index = rng.gen_range(0..self.0.len()); // 随机生成索引
             self.0.get(index) // 返回共享引用
         }
@@ -393,6 +1474,9 @@ pub(crate) struct Expr {
     length: usize,
     depth: usize,
     metadata: String,
+    /// Flows `SOURCE!()` is nested in, from the innermost
+    chain: Vec<String>,
+    recipe: Recipe,
 }
 
 impl Expr {
@@ -402,6 +1486,8 @@ impl Expr {
         length: usize,
         depth: usize,
         metadata: String,
+        chain: Vec<String>,
+        recipe: Recipe,
     ) -> Self {
         let num = format!("{:04}-{}-{}", num, length, depth);
         Expr {
@@ -410,36 +1496,80 @@ impl Expr {
             length,
             depth,
             metadata,
+            chain,
+            recipe,
         }
     }
 
     /// SOURCE!()
     pub(crate) fn source() -> Self {
-        Expr::new(0, String::from("SOURCE!()"), 0, 0, String::from(""))
+        Expr::new(
+            0,
+            String::from("SOURCE!()"),
+            0,
+            0,
+            String::from(""),
+            Vec::new(),
+            Recipe::default(),
+        )
     }
 
     /// SOURCE!() 替换
-    pub(crate) fn fill_source(&self, src: &String) -> String {
+    pub(crate) fn fill_source(&self, src: &str) -> String {
         self.code.replace("SOURCE!()", src)
     }
 }
 
 pub(crate) struct Program {
     code: String,
-    metadata: String, // 注释格式的程序信息
+    metadata: String,  // 注释格式的程序信息
+    sinks: Vec<usize>, // SINK 所在行 (从 1 开始, 相对于合并后的程序)
+    manifest: String,  // harness 的 Cargo.toml
+    kind: Kind,        // 决定哪些报告算作检测到缺陷
 }
 
 impl Program {
-    pub(crate) fn new(code: String, metadata: String) -> Self {
-        Program { code, metadata }
+    /// `sinks` are the lines of the SINK in `code`
+    pub(crate) fn new(code: String, metadata: String, sinks: Vec<usize>) -> Self {
+        // metadata 位于 code 之前
+        let offset = metadata.lines().count();
+        Program {
+            code,
+            sinks: sinks.into_iter().map(|line| line + offset).collect(),
+            metadata,
+            manifest: Manifest::default().render(),
+            kind: Kind::default(),
+        }
+    }
+
+    /// Build the program with the Cargo settings of `testcase`, and judge it by its kind
+    pub(crate) fn of_testcase(mut self, testcase: &Testcase) -> Self {
+        self.manifest = testcase.manifest().render();
+        self.kind = testcase.kind();
+        self
     }
 
     /// Merge `metadata` and `code`
     fn merge(&self) -> String {
-        self.code.clone()
+        format!("{}{}", self.metadata, self.code)
     }
 
-    pub(crate) fn into_harness(&self, harness: &PathBuf) {
+    /// Lines of the SINK in the merged program
+    pub(crate) fn sinks(&self) -> &[usize] {
+        &self.sinks
+    }
+
+    pub(crate) fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn into_harness(&self, harness: &Path) {
+        // 未变化的 Cargo.toml 不重写, 避免 cargo 重新构建
+        let manifest = harness.join("Cargo.toml");
+        if std::fs::read_to_string(&manifest).ok().as_ref() != Some(&self.manifest) {
+            std::fs::write(manifest, &self.manifest).expect("Failed to write");
+        }
         std::fs::write(harness.join("src/main.rs"), self.merge()).expect("Failed to write");
     }
 }
@@ -449,10 +1579,18 @@ pub(crate) struct Config {
     flows: Flows,
     length: usize,
     depth: usize,
+    seed: u64,
+    exhaustive: bool,
 }
 
 impl Config {
-    pub(crate) fn new(config: PathBuf, length: usize, depth: usize) -> Self {
+    pub(crate) fn new(
+        config: PathBuf,
+        length: usize,
+        depth: usize,
+        seed: u64,
+        exhaustive: bool,
+    ) -> Self {
         let testcases = Testcases::from_file(config.join("testcases.yaml"));
         let flows = Flows::from_file(config.join("expressions.yaml"));
         Config {
@@ -460,7 +1598,49 @@ impl Config {
             flows,
             length,
             depth,
+            seed,
+            exhaustive,
+        }
+    }
+
+    /// Indices of the selected testcases [default: ALL]
+    pub(crate) fn targets(&self, selection: &Selection) -> Vec<usize> {
+        if let Some(idx) = selection
+            .indices
+            .iter()
+            .find(|&&idx| idx >= self.testcases.len())
+        {
+            error!(
+                "Error: Index {} is out of bounds. Valid range is 0-{}",
+                idx,
+                self.testcases.len() - 1
+            );
+            std::process::exit(1);
         }
+        let filter = selection.filter();
+        let targets: Vec<_> = (0..self.testcases.len())
+            .filter(|&idx| filter.matches(idx, &self.testcases[idx]))
+            .collect();
+        if targets.is_empty() {
+            error!("Error: No testcase matches the selection");
+            std::process::exit(1);
+        }
+        targets
+    }
+
+    pub(crate) fn generator<'a>(&'a self, testcase: &'a Testcase) -> Generator<'a> {
+        Generator::new(&self.flows, testcase, self.length, self.depth)
+    }
+
+    /// RNG of `testcase`, derived from the seed and its ID
+    ///
+    /// The ID rather than the position in `testcases.yaml` keeps the variants of a testcase
+    /// unchanged when other testcases are added or reordered.
+    pub(crate) fn rng(&self, testcase: &Testcase) -> StdRng {
+        StdRng::seed_from_u64(utils::fnv1a(
+            utils::fnv1a(utils::FNV_OFFSET, &self.seed.to_le_bytes()),
+            testcase.id().as_bytes(),
+        ))
     }
 }
 
@@ -470,21 +1650,180 @@ mod test {
 
     #[test]
     fn test_execute() {
-        let executor = Executor::new(
-            "../tools/eval_home/shell/Safedrop".into(),
-            "./output/harness".into(),
-        );
+        // 以 grep 模拟工具: 报告所有带有 SINK 标记的行
+        let adapter: Adapter = serde_yaml::from_str(
+            r#"
+name: grep
+command: sh
+args:
+  - -c
+  - grep -n SINK {harness}/src/main.rs | sed 's|^|src/main.rs:|'
+parser:
+  type: regex
+  pattern: '(?P<file>[\w./]*main\.rs):(?P<line>\d+)'
+"#,
+        )
+        .unwrap();
+        let executor = Executor::new(adapter, Harnesses::new("./output/harness".into()));
         let program = Program::new(
             r#"
 fn main() {
-    println!("Hello, world!");
+    println!("Hello, world!"); // SINK
 }
 "#
             .to_string(),
             "".to_string(),
+            vec![3],
+        );
+
+        assert!(executor.harnesses.check(&program));
+        let execution = executor.execute(&program);
+        println!("{:#?}", execution.output);
+        assert!(execution.success);
+        assert!(execution.findings[0].hits(program.sinks()));
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let executor = Executor::new(
+            Adapter::executable("/bin/true".into()),
+            Harnesses::new("./output/harness".into()),
+        );
+        let program = Program::new(
+            String::from("fn main() { let _: u8 = \"\"; }"),
+            String::new(),
+            vec![],
+        );
+        assert!(!executor.harnesses.check(&program));
+
+        let mut summary = EvalSummary::new(2, "testcase-002");
+        summary.count(&EvalResults(
+            EvalResult::CompileError,
+            EvalResult::CompileError,
+        ));
+        summary.count(&EvalResults(EvalResult::TP, EvalResult::TN));
+        assert_eq!((summary.variant_count, summary.invalid_count), (1, 1));
+    }
+
+    #[test]
+    fn test_eval_report() {
+        let summary = |results: &[(EvalResult, EvalResult)]| {
+            let mut summary = EvalSummary::new(0, "sp-system-01");
+            for &(pos, neg) in results {
+                summary.count(&EvalResults(pos, neg));
+            }
+            summary
+        };
+        let summaries = [
+            // 3 TP, 1 FN, 1 FP, 3 TN; 2/4 RD
+            summary(&[
+                (EvalResult::TP, EvalResult::TN),
+                (EvalResult::TP, EvalResult::TN),
+                (EvalResult::TP, EvalResult::FP),
+                (EvalResult::FN, EvalResult::TN),
+            ]),
+            // 不报告任何缺陷: 精确率无定义
+            summary(&[(EvalResult::FN, EvalResult::TN)]),
+        ];
+        let report = EvalReport::report(String::from("miri"), &summaries);
+        assert_eq!(report.precision.to_string(), "75.0% (75.0%)");
+        assert_eq!(report.recall.to_string(), "60.0% (37.5%)");
+        assert_eq!(report.robustness.to_string(), "40.0% (25.0%)");
+        assert_eq!(report.true_positive.to_string(), "1 (0)");
+        assert_eq!(Rate::default().to_string(), "- (-)");
+    }
+
+    #[test]
+    fn test_flow_report() {
+        let row = EvalRow::test;
+        let rows = [
+            row("0", None, &[], EvalResults(EvalResult::TP, EvalResult::TN)),
+            row(
+                "1",
+                Some("0"),
+                &["call"],
+                EvalResults(EvalResult::TP, EvalResult::TN),
+            ),
+            row(
+                "2",
+                Some("0"),
+                &["loop"],
+                EvalResults(EvalResult::FN, EvalResult::TN),
+            ),
+            row(
+                "3",
+                Some("1"),
+                &["call", "loop"],
+                EvalResults(EvalResult::TP, EvalResult::FP),
+            ),
+            row(
+                "4",
+                Some("2"),
+                &["loop", "call"],
+                EvalResults(EvalResult::FN, EvalResult::TN),
+            ),
+        ];
+        let rows: Vec<_> = rows.iter().collect();
+        let reports = FlowReport::report(&[String::from("tool")], &rows);
+
+        // 仅父变体鲁棒检测时计为失效
+        let flows: Vec<_> = reports
+            .iter()
+            .map(|r| (r.flow.as_str(), r.variants, r.robust, r.broken))
+            .collect();
+        // 变体计入链中的每个 flow, 失效仅计入最外层的 flow
+        assert_eq!(flows, [("call", 3, 1, 0), ("loop", 3, 0, 2)]);
+    }
+
+    #[test]
+    fn test_execute_timeout() {
+        let adapter: Adapter = serde_yaml::from_str(
+            r#"
+name: sleep
+command: sh
+args: [-c, "sleep 30 & sleep 30"]
+limits:
+  timeout: 1
+parser:
+  type: miri
+"#,
+        )
+        .unwrap();
+        let executor = Executor::new(adapter, Harnesses::new("./output/harness".into()));
+        let program = Program::new(String::from("fn main() {}"), String::new(), vec![]);
+
+        let start = std::time::Instant::now();
+        let execution = executor.execute(&program);
+        assert_eq!(execution.status, adapter::Status::Timeout);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        // 工具退出后, 持有管道的后台子进程不阻塞读取
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "sleep 30 & echo done"]);
+        let start = std::time::Instant::now();
+        let (output, status, _) = utils::run(command, &Limits::default());
+        assert_eq!(
+            (status, output.stdout),
+            (adapter::Status::Finished, b"done\n".to_vec())
         );
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
 
-        let output = executor.execute(0, program);
-        println!("{:#?}", output);
+    #[test]
+    fn test_execute_killed() {
+        let command = || {
+            let mut command = std::process::Command::new("sh");
+            command.args(["-c", "echo 'out of memory' >&2; kill -9 $$"]);
+            command
+        };
+        // 未设置内存限制时, 被杀死或输出 "out of memory" 都不算 OOM
+        let (_, status, _) = utils::run(command(), &Limits::default());
+        assert_eq!(status, adapter::Status::Finished);
+        let limits = Limits {
+            memory: Some(1024),
+            ..Default::default()
+        };
+        let (_, status, _) = utils::run(command(), &limits);
+        assert_eq!(status, adapter::Status::OutOfMemory);
     }
 }
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+
+use crate::{config::Recipe, EvalResult, EvalResults};
+
+/// Robustness failure of a tool on a variant, compared to its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failure {
+    /// The bug of the POS program is detected in the parent, but missed in the variant
+    Miss,
+    /// The NEG program is accepted in the parent, but reported in the variant
+    FalseAlarm,
+}
+
+impl Failure {
+    /// Failure of a variant with `results`, nested in a parent with `parent`
+    pub(crate) fn of(parent: &EvalResults, results: &EvalResults) -> Option<Self> {
+        match (parent, results) {
+            (EvalResults(EvalResult::TP, _), EvalResults(EvalResult::FN, _)) => Some(Failure::Miss),
+            (EvalResults(_, EvalResult::TN), EvalResults(_, EvalResult::FP)) => {
+                Some(Failure::FalseAlarm)
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if the failure still occurs with `results`
+    pub(crate) fn holds(&self, results: &EvalResults) -> bool {
+        match self {
+            Failure::Miss => matches!(results.0, EvalResult::FN),
+            Failure::FalseAlarm => matches!(results.1, EvalResult::FP),
+        }
+    }
+}
+
+/// Greedily simplify `recipe` while `fails` holds
+///
+/// Each round tries the [`Recipe::reductions`] in order and restarts from the first one that
+/// still fails, until none does: removing any flow or filling left then fixes the failure.
+pub(crate) fn minimize<F>(recipe: &Recipe, mut fails: F) -> Recipe
+where
+    F: FnMut(&Recipe) -> bool,
+{
+    let mut recipe = recipe.clone();
+    // 同一 recipe 只评估一次
+    let mut tried = HashSet::new();
+    'reduce: loop {
+        for candidate in recipe.reductions() {
+            if tried.insert(candidate.clone()) && fails(&candidate) {
+                recipe = candidate;
+                continue 'reduce;
+            }
+        }
+        return recipe;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::{Flows, Testcases},
+        Expr,
+    };
+
+    #[test]
+    fn test_minimize() {
+        let testcases = Testcases::from_file("config/testcases.yaml");
+        let flows = Flows::from_file("config/expressions.yaml");
+        let testcase = &testcases[0];
+
+        // Function call -> Array -> loop(if let), 以 Function call 为 EXPRE!() 的填充
+        let source = [Expr::source()];
+        let call = flows[0].into_exprs(1, &source[0], &source, testcase);
+        let call = call.last().unwrap();
+        let exprs = [Expr::source(), call];
+        let mut expr = Expr::source();
+        for flow in [&flows[0], &flows[1], &flows[9]] {
+            expr = flow.into_exprs(2, &expr, &exprs, testcase).last().unwrap();
+        }
+        assert_eq!(expr.recipe.size(), 5);
+        let rebuilt = expr.recipe.build(2, &flows, testcase);
+        assert_eq!(rebuilt.code, expr.code);
+
+        // 模拟仅在 Array 存在时漏报的工具
+        let array = expr.chain[1].clone();
+        let mut calls = 0;
+        let minimal = minimize(&expr.recipe, |recipe| {
+            calls += 1;
+            recipe.build(0, &flows, testcase).chain.contains(&array)
+        });
+        assert_eq!(minimal.size(), 1);
+        assert_eq!(minimal.build(0, &flows, testcase).chain, [array]);
+        assert!(calls < 10);
+
+        let detected = EvalResults(EvalResult::TP, EvalResult::TN);
+        let missed = EvalResults(EvalResult::FN, EvalResult::TN);
+        assert_eq!(Failure::of(&detected, &missed), Some(Failure::Miss));
+        assert_eq!(Failure::of(&missed, &missed), None);
+    }
+}
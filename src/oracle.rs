@@ -0,0 +1,199 @@
+use log::{error, warn};
+use serde::Serialize;
+
+use crate::{adapter::Status, config::Kind, Executor, Program};
+
+/// Behaviour of a program under the reference oracle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum Behaviour {
+    /// The oracle reports a finding, e.g. Undefined Behavior under Miri or a panic
+    Misbehaves,
+    /// The program runs to completion without any finding
+    Clean,
+    /// Timeout, out of memory, or a failure of the oracle itself
+    Inconclusive,
+}
+
+/// Whether the behaviours of a variant agree with its POS/NEG labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum GroundTruth {
+    Valid,
+    /// The POS program is clean, or the NEG program misbehaves
+    Invalid,
+    Inconclusive,
+}
+
+impl GroundTruth {
+    pub(crate) fn of(pos: Behaviour, neg: Behaviour) -> Self {
+        match (pos, neg) {
+            (Behaviour::Clean, _) | (_, Behaviour::Misbehaves) => GroundTruth::Invalid,
+            (Behaviour::Misbehaves, Behaviour::Clean) => GroundTruth::Valid,
+            _ => GroundTruth::Inconclusive,
+        }
+    }
+}
+
+/// Reference oracle of some testcase kinds, run on the programs before any tool is scored
+/// against them
+///
+/// The oracle is an adapter like the evaluated tools, e.g. the `miri` preset or the `run`
+/// preset executing the binary; its findings are not required to land on a SINK line.
+pub(crate) struct Oracle {
+    executor: Executor,
+    kinds: Vec<Kind>,
+}
+
+impl Oracle {
+    pub(crate) fn new(executor: Executor, kinds: Vec<Kind>) -> Self {
+        Oracle { executor, kinds }
+    }
+
+    /// Oracles of `[<kind>=]<tool>` specs, e.g. `panic=run`; a bare tool is the oracle of the
+    /// kinds it observes which no other spec names
+    pub(crate) fn from_specs<F>(specs: &[String], executor: F) -> Vec<Self>
+    where
+        F: Fn(&str) -> Executor,
+    {
+        // 先处理指定类别的 spec, 未指定类别的工具补充其余类别
+        let (named, bare): (Vec<_>, Vec<_>) = specs
+            .iter()
+            .map(|spec| match spec.split_once('=') {
+                Some((kind, tool)) => match Kind::ALL.into_iter().find(|k| k.name() == kind) {
+                    Some(kind) => (Some(kind), tool),
+                    None => {
+                        error!(
+                            "Error: Unknown testcase kind `{}` in oracle `{}`",
+                            kind, spec
+                        );
+                        std::process::exit(1);
+                    }
+                },
+                None => (None, spec.as_str()),
+            })
+            .partition(|(kind, _)| kind.is_some());
+
+        let mut oracles: Vec<Oracle> = Vec::new();
+        let mut assigned: Vec<Kind> = Vec::new();
+        for (kind, tool) in named {
+            let kind = kind.unwrap();
+            if assigned.contains(&kind) {
+                error!("Error: Several oracles of {} testcases", kind.name());
+                std::process::exit(1);
+            }
+            assigned.push(kind);
+            let executor = executor(tool);
+            if !executor.adapter.observes.contains(&kind) {
+                error!(
+                    "Error: Oracle {} cannot observe {} testcases",
+                    executor.name(),
+                    kind.name()
+                );
+                std::process::exit(1);
+            }
+            match oracles.iter_mut().find(|o| o.name() == executor.name()) {
+                Some(oracle) => oracle.kinds.push(kind),
+                None => oracles.push(Oracle::new(executor, vec![kind])),
+            }
+        }
+        for (_, tool) in bare {
+            let executor = executor(tool);
+            let kinds: Vec<_> = executor
+                .adapter
+                .observes
+                .iter()
+                .filter(|kind| !assigned.contains(kind))
+                .copied()
+                .collect();
+            if kinds.is_empty() {
+                warn!(
+                    "Oracle {} is not used by any testcase kind",
+                    executor.name()
+                );
+                continue;
+            }
+            assigned.extend(kinds.iter().copied());
+            match oracles.iter_mut().find(|o| o.name() == executor.name()) {
+                Some(oracle) => oracle.kinds.extend(kinds),
+                None => oracles.push(Oracle::new(executor, kinds)),
+            }
+        }
+        oracles
+    }
+
+    /// Check if the oracle judges the testcases of `kind`
+    pub(crate) fn observes(&self, kind: Kind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    pub(crate) fn name(&self) -> String {
+        self.executor.name()
+    }
+
+    /// Behaviour of `program`; inconclusive if the oracle cannot observe its kind of bug
+    pub(crate) fn behaviour(&self, program: &Program) -> Behaviour {
+        if !self.observes(program.kind()) {
+            return Behaviour::Inconclusive;
+        }
+        let execution = self.executor.execute(program);
+        if execution.status != Status::Finished {
+            Behaviour::Inconclusive
+        } else if execution
+            .findings
+            .iter()
+            .any(|finding| program.kind().concerns(finding))
+        {
+            Behaviour::Misbehaves
+        } else if execution.success {
+            Behaviour::Clean
+        } else {
+            Behaviour::Inconclusive
+        }
+    }
+
+    /// Behaviours of the POS and NEG programs
+    pub(crate) fn verify(&self, (pos, neg): (&Program, &Program)) -> (Behaviour, Behaviour) {
+        (self.behaviour(pos), self.behaviour(neg))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{adapter::Adapters, harness::Harnesses};
+
+    #[test]
+    fn test_run_oracle() {
+        let harnesses = Harnesses::new("./output/oracle".into());
+        let executor =
+            |tool: &str| Executor::new(Adapters::default().resolve(tool), harnesses.clone());
+        // run 只能观察 panic, 其余类别由指定的 oracle 判断
+        let specs = [String::from("run"), String::from("ub=miri")];
+        let oracles = Oracle::from_specs(&specs, executor);
+        let kinds: Vec<_> = oracles
+            .iter()
+            .map(|o| (o.name(), o.kinds.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (String::from("miri"), vec![Kind::Ub]),
+                (String::from("run"), vec![Kind::Panic])
+            ]
+        );
+
+        let oracle = &oracles[1];
+        let program = |code: &str| Program {
+            kind: Kind::Panic,
+            ..Program::new(code.to_string(), String::new(), vec![])
+        };
+        let pos = program("fn main() { let v: Vec<u8> = vec![]; v[0]; }");
+        let neg = program("fn main() { let v: Vec<u8> = vec![0]; v[0]; }");
+        let (pos, neg) = oracle.verify((&pos, &neg));
+        assert_eq!((pos, neg), (Behaviour::Misbehaves, Behaviour::Clean));
+        assert_eq!(GroundTruth::of(pos, neg), GroundTruth::Valid);
+        assert_eq!(GroundTruth::of(neg, neg), GroundTruth::Invalid);
+        // 正常结束的 UB 程序不判为 Clean
+        let ub = Program::new(String::from("fn main() {}"), String::new(), vec![]);
+        assert_eq!(oracle.behaviour(&ub), Behaviour::Inconclusive);
+    }
+}
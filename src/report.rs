@@ -0,0 +1,207 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use tabled::Tabled;
+
+use crate::{
+    eval_tree::{color, RESULTS},
+    utils::escape,
+    EvalResult, EvalResults, EvalRow,
+};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 2px 8px; text-align: right; }
+th { background: #f4f4f4; }
+ul.tree, ul.tree ul { list-style: none; padding-left: 1.5em; margin: 0; }
+summary { cursor: pointer; }
+.dot { display: inline-block; width: 0.8em; height: 0.8em; border: 1px solid #666; \
+border-radius: 50%; margin-right: 0.3em; }
+.leaf { margin-left: 1.1em; }
+.flows { color: #666; }
+a { margin-left: 0.3em; }";
+
+const SCRIPT: &str = "\
+function toggle(open) {
+  document.querySelectorAll('#trees details').forEach(d => d.open = open);
+}";
+
+/// HTML table of `rows`, with the headers of their tabled report
+pub(crate) fn table<T: Tabled>(rows: &[T]) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for header in T::headers() {
+        let _ = write!(html, "<th>{}</th>", escape(&header));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for field in row.fields() {
+            let _ = write!(html, "<td>{}</td>", escape(&field));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Self-contained report with the summary `sections` (title and HTML content), and the
+/// evaluation tree of every tool on every testcase
+///
+/// The report is written to the output directory of the run; each node links to the sources and
+/// tool outputs of its variant, relative to that directory.
+pub(crate) fn html(sections: &[(String, String)], tools: &[String], rows: &[&EvalRow]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Evaluation report</title>\n<style>\n{}\n</style>\n<script>\n{}\n</script>\n\
+         </head>\n<body>\n<h1>Evaluation report</h1>\n",
+        STYLE, SCRIPT
+    );
+    for (title, content) in sections {
+        let _ = write!(html, "<h2>{}</h2>\n{}", escape(title), content);
+    }
+
+    html.push_str("<h2>Evaluation trees</h2>\n<p>");
+    for res in RESULTS {
+        let _ = write!(
+            html,
+            "<span class=\"dot\" style=\"background:{}\"></span>{} ",
+            color(res),
+            res.name()
+        );
+    }
+    html.push_str(
+        "</p>\n<p><button onclick=\"toggle(true)\">Expand all</button> \
+         <button onclick=\"toggle(false)\">Collapse all</button></p>\n<div id=\"trees\">\n",
+    );
+    for (i, tool) in tools.iter().enumerate() {
+        let _ = writeln!(html, "<h3>{}</h3>", escape(tool));
+        for testcase in rows.chunk_by(|a, b| a.id == b.id) {
+            tree(&mut html, tool, i, testcase);
+        }
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+/// Collapsible tree of the variants of one testcase
+fn tree(html: &mut String, tool: &str, i: usize, rows: &[&EvalRow]) {
+    let mut children: HashMap<Option<&str>, Vec<&EvalRow>> = HashMap::new();
+    for row in rows {
+        children.entry(row.parent.as_deref()).or_default().push(row);
+    }
+    let wrong = rows
+        .iter()
+        .filter(|row| !matches!(row.results[i], EvalResults(EvalResult::TP, EvalResult::TN)))
+        .count();
+    let _ = writeln!(
+        html,
+        "<details><summary>{} ({} variants, {} not TP/TN)</summary>\n<ul class=\"tree\">",
+        escape(&rows[0].id),
+        rows.len(),
+        wrong
+    );
+    for root in children.get(&None).into_iter().flatten() {
+        node(html, tool, i, root, &children);
+    }
+    html.push_str("</ul>\n</details>\n");
+}
+
+fn node(
+    html: &mut String,
+    tool: &str,
+    i: usize,
+    row: &EvalRow,
+    children: &HashMap<Option<&str>, Vec<&EvalRow>>,
+) {
+    let res = row.results[i];
+    let mut label = format!(
+        "<span class=\"dot\" style=\"background:{}\"></span><b>{}</b> {}",
+        color(res),
+        escape(&row.variant),
+        res.name()
+    );
+    if !row.chain.is_empty() {
+        let _ = write!(
+            label,
+            " <span class=\"flows\">[{}]</span>",
+            escape(&row.chain.join(" > "))
+        );
+    }
+    // 与 evalTree.json 中的 outputs 相同, 工具未在无效变体上运行
+    let dir = format!("{}/{}/{}", tool, row.id, row.variant);
+    for program in ["POS", "NEG"] {
+        let _ = write!(
+            label,
+            " &middot; <a href=\"{}/{}.rs\">{}</a>",
+            escape(&dir),
+            program,
+            program
+        );
+        if !res.is_invalid() {
+            for ext in ["stdout", "stderr", "json"] {
+                let _ = write!(
+                    label,
+                    "<a href=\"{}/{}.{}\">{}</a>",
+                    escape(&dir),
+                    program,
+                    ext,
+                    ext
+                );
+            }
+        }
+    }
+    match children.get(&Some(row.variant.as_str())) {
+        Some(nodes) => {
+            let _ = writeln!(html, "<li><details><summary>{}</summary>\n<ul>", label);
+            for child in nodes {
+                node(html, tool, i, child, children);
+            }
+            html.push_str("</ul></details></li>\n");
+        }
+        None => {
+            let _ = writeln!(html, "<li><span class=\"leaf\">{}</span></li>", label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let row = |variant, parent, res| EvalRow::test(variant, parent, &["Array"], res);
+        let rows = [
+            row(
+                "0000-0-0",
+                None,
+                EvalResults(EvalResult::TP, EvalResult::TN),
+            ),
+            row(
+                "0001-1-1",
+                Some("0000-0-0"),
+                EvalResults(EvalResult::FN, EvalResult::TN),
+            ),
+            row(
+                "0002-1-1",
+                Some("0000-0-0"),
+                EvalResults(EvalResult::CompileError, EvalResult::CompileError),
+            ),
+        ];
+        let rows: Vec<_> = rows.iter().collect();
+        let sections = [(String::from("Summary"), String::from("<p>a</p>\n"))];
+        let html = html(&sections, &[String::from("miri")], &rows);
+
+        assert!(html.contains("<h2>Summary</h2>\n<p>a</p>"));
+        assert!(html.contains("sp-system-01 (3 variants, 2 not TP/TN)"));
+        // 根节点可折叠, 子节点为叶
+        assert!(
+            html.contains("<li><details><summary><span class=\"dot\" style=\"background:green\">")
+        );
+        assert!(html.contains("<a href=\"miri/sp-system-01/0001-1-1/POS.rs\">POS</a><a href=\"miri/sp-system-01/0001-1-1/POS.stdout\">stdout</a>"));
+        // 无法编译的变体没有工具输出
+        assert!(html.contains("0002-1-1/NEG.rs\">NEG</a></span></li>"));
+        assert!(html.contains("[Array]"));
+        assert!(!html.contains("src="));
+    }
+}
@@ -1,31 +1,23 @@
 use std::{
-    ffi::OsStr,
     fs::{self, File},
-    io::{self},
-    os::unix::fs::PermissionsExt as _,
+    io::{self, Read as _},
+    os::unix::{
+        fs::PermissionsExt as _,
+        process::{CommandExt as _, ExitStatusExt as _},
+    },
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
-use log::info;
+use log::warn;
+use tabled::Tabled;
 
-use crate::{EvalResult, EvalResults, EvalSummary, Program};
-
-/// Usage: `cargo new --vcs none --edtion 2018 harness`
-pub(crate) fn generate_harness<P>(path: P)
-where
-    P: AsRef<OsStr>,
-{
-    let output = Command::new("cargo")
-        .arg("new")
-        .args(["--vcs", "none", "--edition", "2018"])
-        .arg(&path)
-        .output()
-        .expect("cargo command failed to start");
-    if output.status.success() {
-        info!("Generate Harness: {}", path.as_ref().to_str().unwrap());
-    }
-}
+use crate::{
+    adapter::{Execution, Limits, Status},
+    EvalResult, EvalResults, EvalRow, EvalSummary, Program, TruthRow,
+};
 
 /// Check if `path` points to an executable file
 pub(crate) fn is_executable<P>(path: P)
@@ -47,21 +39,130 @@ where
     }
 }
 
+/// Run `command` under `limits`
+///
+/// The command runs in its own process group, which is killed as a whole when the wall-clock
+/// timeout expires, and once the command exits so that no background child keeps its pipes
+/// open. Memory and CPU limits are applied with `setrlimit` and inherited by every
+/// child process.
+pub(crate) fn run(mut command: Command, limits: &Limits) -> (Output, Status, Duration) {
+    let Limits { memory, cpu, .. } = *limits;
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    // SAFETY: `setrlimit` is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            let set = |resource, soft: u64, hard: u64| {
+                let rlimit = libc::rlimit {
+                    rlim_cur: soft as libc::rlim_t,
+                    rlim_max: hard as libc::rlim_t,
+                };
+                match libc::setrlimit(resource, &rlimit) {
+                    0 => Ok(()),
+                    _ => Err(io::Error::last_os_error()),
+                }
+            };
+            if let Some(memory) = memory {
+                set(libc::RLIMIT_AS, memory * 1024 * 1024, memory * 1024 * 1024)?;
+            }
+            if let Some(cpu) = cpu {
+                // 软限制触发 SIGXCPU, 与 OOM killer 的 SIGKILL 区分
+                set(libc::RLIMIT_CPU, cpu, cpu + 1)?;
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().expect("Tool failed to execute");
+    // 并发读取管道, 避免子进程因管道写满而阻塞
+    let pipe = |mut reader: Box<dyn io::Read + Send>| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = reader.read_to_end(&mut buf);
+            buf
+        })
+    };
+    let stdout = pipe(Box::new(child.stdout.take().unwrap()));
+    let stderr = pipe(Box::new(child.stderr.take().unwrap()));
+
+    let start = Instant::now();
+    let mut timeout = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().expect("Tool failed to execute") {
+            break status;
+        }
+        if limits
+            .timeout
+            .is_some_and(|secs| start.elapsed() >= Duration::from_secs(secs))
+        {
+            warn!("Timeout after {:?}: {:?}", start.elapsed(), command);
+            // 杀死整个进程组 (包括 cargo 启动的子进程)
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            timeout = true;
+            break child.wait().expect("Tool failed to execute");
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    // 后台子进程仍持有管道时, 读取线程不会结束
+    unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+
+    let elapsed = start.elapsed();
+    let output = Output {
+        status,
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap(),
+    };
+    let status = if timeout || output.status.signal() == Some(libc::SIGXCPU) {
+        Status::Timeout
+    } else if is_out_of_memory(&output, limits) {
+        Status::OutOfMemory
+    } else {
+        Status::Finished
+    };
+    (output, status, elapsed)
+}
+
+/// Check if the process ran out of its memory limit: it was killed by the OOM killer (SIGKILL),
+/// or aborted by Rust's allocation failure handler (SIGABRT)
+///
+/// Without a memory limit, such a signal is a failure of the tool rather than an OOM, and an
+/// allocation failure of the analysed program is reported by the tool like any other bug.
+pub(crate) fn is_out_of_memory(output: &Output, limits: &Limits) -> bool {
+    limits.memory.is_some() && matches!(output.status.signal(), Some(libc::SIGKILL | libc::SIGABRT))
+}
+
 /// 评估逻辑实现
-/// TODO: 标准化 + 解析器
-pub(crate) fn evaluate((pos, neg): (Output, Output)) -> EvalResults {
-    let pos_res =  if pos.status.success() {
-        match pos.stdout.is_empty() {
-            true => EvalResult::FN, // 漏报
-            false => EvalResult::TP,
+///
+/// A program is considered detected only if the tool reports a finding of its kind, on one of
+/// its SINK lines for UB. Programs without SINK markers are detected by any finding in the
+/// harness.
+pub(crate) fn evaluate(
+    (pos, neg): (Execution, Execution),
+    (pos_prog, neg_prog): (&Program, &Program),
+) -> EvalResults {
+    let pos_res = if pos.status == Status::Timeout {
+        EvalResult::Timeout
+    } else if pos.status == Status::OutOfMemory {
+        EvalResult::OutOfMemory
+    } else if pos.success {
+        match is_detected(&pos, pos_prog) {
+            false => EvalResult::FN, // 漏报
+            true => EvalResult::TP,
         }
     } else {
         EvalResult::Err
     };
-    let neg_res =  if neg.status.success() {
-        match neg.stdout.is_empty() {
-            true => EvalResult::TN,
-            false => EvalResult::FP, // 误报
+    let neg_res = if neg.status == Status::Timeout {
+        EvalResult::Timeout
+    } else if neg.status == Status::OutOfMemory {
+        EvalResult::OutOfMemory
+    } else if neg.success {
+        match is_detected(&neg, neg_prog) {
+            false => EvalResult::TN,
+            true => EvalResult::FP, // 误报
         }
     } else {
         EvalResult::Err
@@ -69,12 +170,83 @@ pub(crate) fn evaluate((pos, neg): (Output, Output)) -> EvalResults {
     EvalResults(pos_res, neg_res)
 }
 
+/// Check if any finding of `execution` detects the bug of `program`: for UB, a finding on a
+/// SINK line; for panics and leaks, a finding of that kind anywhere in `main.rs`
+fn is_detected(execution: &Execution, program: &Program) -> bool {
+    execution
+        .findings
+        .iter()
+        .any(|finding| program.kind().detects(finding, program.sinks()))
+}
+
 pub(crate) fn write(path: PathBuf, (pos, neg): (&Program, &Program)) {
     std::fs::create_dir_all(&path).expect(" std::fs::create_dir_all failed");
     std::fs::write(path.join("POS.rs"), pos.merge()).expect("std::fs::write failed");
     std::fs::write(path.join("NEG.rs"), neg.merge()).expect("std::fs::write failed");
 }
 
+/// Offset basis of [`fnv1a`]
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a hash of `bytes`, continued from `hash`; stable across platforms and Rust versions
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Fingerprint of the sources written by [`write`] in `path`, without their metadata comments
+///
+/// The fingerprint identifies a variant by its content across runs, whatever its number; it is
+/// `None` if the sources are missing.
+pub(crate) fn fingerprint(path: &Path) -> Option<String> {
+    let mut hash = FNV_OFFSET;
+    for name in ["POS.rs", "NEG.rs"] {
+        let code = fs::read_to_string(path.join(name)).ok()?;
+        let start = code
+            .lines()
+            .take_while(|line| line.starts_with("//"))
+            .count();
+        for line in code.lines().skip(start) {
+            hash = fnv1a(fnv1a(hash, line.as_bytes()), b"\n");
+        }
+    }
+    Some(format!("{:016x}", hash))
+}
+
+/// Write the stdout, stderr, exit status and wall time of the tool on the `name` program
+/// (`POS` or `NEG`) next to its source in `path`, with the SINK lines where a bug is expected
+pub(crate) fn write_execution(
+    path: &Path,
+    name: &str,
+    execution: &Execution,
+    program: &Program,
+) -> io::Result<()> {
+    let output = &execution.output;
+    fs::write(path.join(format!("{}.stdout", name)), &output.stdout)?;
+    fs::write(path.join(format!("{}.stderr", name)), &output.stderr)?;
+    let summary = serde_json::json!({
+        "status": execution.status,
+        "code": output.status.code(),
+        "signal": output.status.signal(),
+        "success": execution.success,
+        "time": execution.elapsed.as_secs_f64(),
+        "findings": execution.findings,
+        "sinks": program.sinks(),
+    });
+    let file = File::create(path.join(format!("{}.json", name)))?;
+    serde_json::to_writer_pretty(file, &summary)?;
+    Ok(())
+}
+
+/// Escape `text` for the content of an XML element or the value of an attribute
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 /// 保存 DOT 内容并生成图片
 ///
 /// # Arguments
@@ -104,7 +276,7 @@ pub(crate) fn generate_image_from_dot(
 
     // 调用 Graphviz 生成图片
     let output = Command::new("dot")
-        .args(&["-T", &format, "-o"])
+        .args(["-T", &format, "-o"])
         .arg(&output_path)
         .arg(&dot_path)
         .output();
@@ -143,4 +315,60 @@ pub(crate) fn serialize_to_csv(summaries: &[EvalSummary], output_file: PathBuf)
     writer.flush()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Write the behaviour of every variant under the oracle
+pub(crate) fn serialize_truths(rows: &[TruthRow], output_file: PathBuf) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(File::create(output_file)?);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write `rows` to the CSV, with the same headers and cells as the printed table
+pub(crate) fn serialize_table<T: Tabled>(rows: &[T], output_file: PathBuf) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(File::create(output_file)?);
+    writer.write_record(T::headers().iter().map(|header| header.as_ref()))?;
+    for row in rows {
+        writer.write_record(row.fields().iter().map(|field| field.as_ref()))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write one row per variant with the result of every tool
+pub(crate) fn serialize_matrix(
+    tools: &[String],
+    rows: &[&EvalRow],
+    output_file: PathBuf,
+) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(File::create(output_file)?);
+
+    let mut header = vec![
+        String::from("编号"),
+        String::from("ID"),
+        String::from("变体"),
+        String::from("父变体"),
+        String::from("流"),
+    ];
+    header.extend(tools.iter().cloned());
+    writer.write_record(&header)?;
+
+    for row in rows {
+        writer.write_field(format!("{:03}", row.idx))?;
+        writer.write_field(&row.id)?;
+        writer.write_field(&row.variant)?;
+        writer.write_field(row.parent.as_deref().unwrap_or_default())?;
+        writer.write_field(row.chain.join(" > "))?;
+        for res in &row.results {
+            // 复用 EvalResults 的序列化格式
+            writer.write_field(serde_json::to_value(res)?.as_str().unwrap_or_default())?;
+        }
+        writer.write_record(None::<&[u8]>)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
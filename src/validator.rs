@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::{
+    adapter::Adapters,
+    config::{Flows, Testcases},
+};
+
+/// Placeholders expanded by the generator
+const PLACEHOLDERS: [&str; 5] = ["SOURCE", "EXPRE", "TYPE", "VALUE", "COND"];
+/// Tag prefixes, e.g. `SP(Initialized)`; `CQ()` may be empty
+const TAXONOMY: [&str; 4] = ["SP", "UB", "TY", "CQ"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+/// Problem found in a configuration file, located by 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            severity,
+            self.message
+        )
+    }
+}
+
+/// Check a configuration file, guessing its kind from the file name: `expressions*.yaml`,
+/// `tools*.yaml`, or else testcases
+pub(crate) fn validate(path: &Path) -> Vec<Diagnostic> {
+    let cnt = match std::fs::read_to_string(path) {
+        Ok(cnt) => cnt,
+        Err(err) => {
+            let mut file = File::new(path, "");
+            file.report(Severity::Error, (1, 1), err.to_string());
+            return file.diagnostics;
+        }
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if name.starts_with("expressions") {
+        validate_flows(path, &cnt)
+    } else if name.starts_with("tools") {
+        validate_tools(path, &cnt)
+    } else {
+        validate_testcases(path, &cnt)
+    }
+}
+
+pub(crate) fn validate_testcases(path: &Path, cnt: &str) -> Vec<Diagnostic> {
+    let mut file = File::new(path, cnt);
+    let base = path.parent().unwrap_or(Path::new("."));
+    let testcases = match Testcases::parse(cnt, base) {
+        Ok(testcases) => testcases,
+        Err(err) => return file.yaml(err),
+    };
+
+    let mut ids = HashMap::new();
+    let mut descriptions: HashMap<&str, usize> = HashMap::new();
+    for (idx, testcase) in testcases.iter().enumerate() {
+        let id = testcase.id();
+        if let Some(&first) = ids.get(id) {
+            let at = file.find(idx, "id:");
+            let message = format!("duplicate ID '{}', first used by testcase {}", id, first);
+            file.report(Severity::Error, at, message);
+        }
+        ids.entry(id).or_insert(idx);
+        if let Some(&first) = descriptions.get(testcase.description()) {
+            let at = file.find(idx, "description:");
+            let message = format!("{}: same description as {}", id, testcases[first].id());
+            file.report(Severity::Warning, at, message);
+        }
+        descriptions.entry(testcase.description()).or_insert(idx);
+        for (key, case) in testcase.cases() {
+            if !case.code().contains("SOURCE!()") {
+                let at = file.find(idx, &format!("{}:", key));
+                let message = format!("{}: {}.code does not contain SOURCE!()", id, key);
+                file.report(Severity::Error, at, message);
+            }
+        }
+        for tag in testcase.tags() {
+            let at = file.find(idx, tag);
+            match tag.split_once('(') {
+                Some((prefix, value)) if value.ends_with(')') => {
+                    if !TAXONOMY.contains(&prefix) {
+                        let message = format!(
+                            "{}: unknown tag '{}', expected one of {}",
+                            id,
+                            tag,
+                            TAXONOMY.map(|prefix| format!("{}(...)", prefix)).join(", ")
+                        );
+                        file.report(Severity::Error, at, message);
+                    } else if value == ")" && prefix != "CQ" {
+                        let message = format!("{}: empty tag '{}'", id, tag);
+                        file.report(Severity::Error, at, message);
+                    }
+                }
+                _ => {
+                    let message =
+                        format!("{}: malformed tag '{}', expected PREFIX(value)", id, tag);
+                    file.report(Severity::Error, at, message);
+                }
+            }
+        }
+        // testcase 中仅 SOURCE!() 会被展开
+        file.placeholders(idx, &["SOURCE"]);
+    }
+    file.diagnostics
+}
+
+pub(crate) fn validate_flows(path: &Path, cnt: &str) -> Vec<Diagnostic> {
+    let mut file = File::new(path, cnt);
+    let flows = match Flows::parse(cnt) {
+        Ok(flows) => flows,
+        Err(err) => return file.yaml(err),
+    };
+    for (idx, flow) in flows.iter().enumerate() {
+        let count = flow.code().matches("SOURCE!()").count();
+        if count != 1 {
+            let at = file.find(idx, "code:");
+            let message = format!(
+                "flow '{}' contains {} SOURCE!(), expected exactly one",
+                flow.name(),
+                count
+            );
+            file.report(Severity::Error, at, message);
+        }
+        file.placeholders(idx, &PLACEHOLDERS);
+    }
+    file.diagnostics
+}
+
+pub(crate) fn validate_tools(path: &Path, cnt: &str) -> Vec<Diagnostic> {
+    let file = File::new(path, cnt);
+    match serde_yaml::from_str::<Adapters>(cnt) {
+        Ok(_) => file.diagnostics,
+        Err(err) => file.yaml(err),
+    }
+}
+
+/// Content of a YAML file being checked, with the first line of each top-level item
+struct File<'a> {
+    path: &'a Path,
+    lines: Vec<&'a str>,
+    items: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> File<'a> {
+    fn new(path: &'a Path, cnt: &'a str) -> Self {
+        let lines: Vec<&str> = cnt.lines().collect();
+        let items = (0..lines.len())
+            .filter(|&line| lines[line].starts_with("- "))
+            .collect();
+        File {
+            path,
+            lines,
+            items,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, severity: Severity, (line, column): (usize, usize), message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    /// Report a syntax or schema error at the location given by the YAML parser
+    fn yaml(mut self, err: serde_yaml::Error) -> Vec<Diagnostic> {
+        let at = err
+            .location()
+            .map_or((1, 1), |location| (location.line(), location.column()));
+        self.report(Severity::Error, at, err.to_string());
+        self.diagnostics
+    }
+
+    /// Lines of the `idx`-th item (0-based)
+    fn range(&self, idx: usize) -> std::ops::Range<usize> {
+        match self.items.get(idx) {
+            Some(&start) => start..self.items.get(idx + 1).copied().unwrap_or(self.lines.len()),
+            // 条目不以 "- " 开头 (如 flow 风格), 无法定位
+            None => 0..0,
+        }
+    }
+
+    /// Location of the first `needle` in the `idx`-th item, or else of the item itself
+    fn find(&self, idx: usize, needle: &str) -> (usize, usize) {
+        let range = self.range(idx);
+        let start = range.start;
+        range
+            .into_iter()
+            .find_map(|line| {
+                let column = self.lines[line].find(needle)?;
+                Some((line + 1, column + 1))
+            })
+            .unwrap_or((start + 1, 1))
+    }
+
+    /// Report placeholders of the `idx`-th item which are misspelled or not in `allowed`
+    fn placeholders(&mut self, idx: usize, allowed: &[&str]) {
+        let re = Regex::new(r"\b([A-Z][A-Z0-9_]*)!\(").unwrap();
+        let mut found = Vec::new();
+        for line in self.range(idx) {
+            for captures in re.captures_iter(self.lines[line]) {
+                let name = captures.get(1).unwrap();
+                found.push((name.as_str(), (line + 1, name.start() + 1)));
+            }
+        }
+        for (name, at) in found {
+            if allowed.contains(&name) {
+                continue;
+            }
+            let message = if PLACEHOLDERS.contains(&name) {
+                format!("{}!() is not expanded here", name)
+            } else if let Some(similar) = PLACEHOLDERS.iter().find(|p| distance(name, p) <= 2) {
+                format!(
+                    "unknown placeholder {}!(), did you mean {}!()?",
+                    name, similar
+                )
+            } else {
+                // 其他大写宏 (如用户定义的宏) 不检查
+                continue;
+            };
+            self.report(Severity::Error, at, message);
+        }
+    }
+}
+
+/// Levenshtein distance between `a` and `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = prev + usize::from(ca != cb);
+            prev = row[j + 1];
+            row[j + 1] = cost.min(prev + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_config() {
+        for path in [
+            "config/testcases.yaml",
+            "config/testcases-90.yaml",
+            "config/expressions.yaml",
+            "config/expressions-22.yaml",
+            "config/tools.yaml",
+        ] {
+            let errors: Vec<_> = validate(Path::new(path))
+                .into_iter()
+                .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                .collect();
+            assert!(errors.is_empty(), "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let path = Path::new("expressions.yaml");
+        let diagnostics = validate_flows(
+            path,
+            "- name: Call\n  code: |-\n    call(SOURCE!(), EXPR!(x))\n- name: Nothing\n  code: x\n",
+        );
+        let messages: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "expressions.yaml:3:21: error: unknown placeholder EXPR!(), did you mean EXPRE!()?",
+                "expressions.yaml:5:3: error: flow 'Nothing' contains 0 SOURCE!(), expected exactly one",
+            ]
+        );
+
+        // YAML 错误带有行列号
+        let diagnostics = validate_flows(path, "- name: Call\n  code: [\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 9));
+
+        assert_eq!(distance("EXPR", "EXPRE"), 1);
+        assert_eq!(distance("SOURCE", "SORUCE"), 2);
+    }
+}